@@ -0,0 +1,17 @@
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use pwmp_client::async_client::AsyncPwmpClient;
+use pwmp_types::mac::Mac;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let mut client = AsyncPwmpClient::new(
+        SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 55300),
+        Mac::new(1, 2, 3, 4, 5, 6),
+    )
+    .await
+    .unwrap();
+
+    assert!(client.ping().await);
+    client.close().await.unwrap();
+}
@@ -15,4 +15,6 @@ fn main() {
     for i in 0..settings.len() {
         println!("{:?} => {:?}", settings[i], values[i]);
     }
+
+    client.close().unwrap();
 }
@@ -11,4 +11,6 @@ fn main() {
     .unwrap();
 
     assert!(client.ping());
+
+    client.close().unwrap();
 }
@@ -7,4 +7,6 @@ fn main() {
     client
         .post_measurements(Default::default(), 100, None)
         .unwrap();
+
+    client.close().unwrap();
 }
@@ -0,0 +1,375 @@
+use crate::{
+    builder::{Options, PwmpClientBuilder},
+    error::Error,
+    expect_ok, expect_settings, expect_settings_map, expect_welcome, is_idempotent, parse_response,
+    settings::{NodeSettings, SettingsMap},
+    Result,
+};
+use pwmp_types::{
+    aliases::{
+        AirPressure, Altitude, BatteryVoltage, Humidity, Latitude, Longitude, Rssi, SessionToken,
+        Temperature,
+    },
+    borrowed::ResponseRef,
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity, MAX_NOTIFICATION_LEN},
+    request::{Request, NEW_SESSION},
+    setting::SettingName,
+    Message,
+};
+use std::{future::Future, io, net::SocketAddr, time::Duration, vec::Vec};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, ToSocketAddrs},
+    time::timeout,
+};
+
+#[allow(clippy::doc_markdown)]
+/// Asynchronous PixelWeather Messaging Protocol Client, built on top of Tokio.
+///
+/// Provides the same operations and retry behavior as [`PwmpClient`](crate::PwmpClient).
+/// Since asynchronous code cannot run in `Drop`, the session should be ended using
+/// [`close()`](Self::close). If the client is dropped instead, the connection is simply closed
+/// and the server will keep the session around until it can no longer be resumed.
+pub struct AsyncPwmpClient {
+    socket: TcpStream,
+    addrs: Vec<SocketAddr>,
+    mac: Mac,
+    session: Option<SessionToken>,
+    options: Options,
+}
+
+impl AsyncPwmpClient {
+    /// Create a new client by connecting to a PWMP server using the default options.
+    /// Use [`PwmpClientBuilder::build_async()`] to customize them.
+    ///
+    /// # Errors
+    /// If the server rejects the client (for eg. if it's unathorized)
+    /// an `Err(Error::Reject)` is returned. An error is also returned
+    /// if a generic I/O error occurred.
+    pub async fn new<A: ToSocketAddrs>(addr: A, mac: Mac) -> Result<Self> {
+        PwmpClientBuilder::new(mac).build_async(addr).await
+    }
+
+    pub(crate) async fn with_options(
+        addrs: impl Iterator<Item = SocketAddr>,
+        mac: Mac,
+        options: Options,
+    ) -> Result<Self> {
+        let addrs: Vec<_> = addrs.collect();
+        let mut retry = 0;
+
+        let connection = loop {
+            match Connection::open(&addrs, &mac, None, &options).await {
+                Err(Error::Io(_)) if retry + 1 < options.retry.max_attempts() => {
+                    retry += 1;
+                    options.backoff_async(retry).await?;
+                }
+                result => break result?,
+            }
+        };
+
+        Ok(Self {
+            socket: connection.socket,
+            addrs,
+            mac,
+            session: connection.session,
+            options,
+        })
+    }
+
+    /// Try to ping the server. Returns whether the server responded correctly.
+    /// On an I/O error, `false` is returned.
+    pub async fn ping(&mut self) -> bool {
+        let mut buf = self.options.buffer();
+        let Ok(response) = self.request(Request::Ping, &mut buf).await else {
+            return false;
+        };
+
+        response == ResponseRef::Pong
+    }
+
+    /// Get values of multiple settings.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub async fn get_settings<const N: usize>(
+        &mut self,
+        settings: [SettingName; N],
+    ) -> Result<[SettingValue; N]> {
+        let mut buf = self.options.buffer();
+        let response = self
+            .request(Request::GetSettings(settings.into()), &mut buf)
+            .await?;
+
+        expect_settings(response)
+    }
+
+    /// Get values of multiple settings, without knowing their amount at compile time.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub async fn get_settings_dyn(&mut self, settings: &[SettingName]) -> Result<SettingsMap> {
+        let mut buf = self.options.buffer();
+        let response = self
+            .request(Request::GetSettings(settings.into()), &mut buf)
+            .await?;
+
+        expect_settings_map(response, settings)
+    }
+
+    /// Get all settings of the node. Missing values are replaced with their defaults.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub async fn fetch_node_settings(&mut self) -> Result<NodeSettings> {
+        let values = self.get_settings_dyn(&NodeSettings::NAMES).await?;
+        Ok(NodeSettings::from_values(&values))
+    }
+
+    /// Post node measurements.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub async fn post_measurements(
+        &mut self,
+        temperature: Temperature,
+        humidity: Humidity,
+        air_pressure: Option<AirPressure>,
+    ) -> Result<()> {
+        self.request_ok(Request::PostResults {
+            temperature,
+            humidity,
+            air_pressure,
+        })
+        .await
+    }
+
+    /// Post node stats.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub async fn post_stats(
+        &mut self,
+        battery: BatteryVoltage,
+        wifi_ssid: &str,
+        wifi_rssi: Rssi,
+    ) -> Result<()> {
+        self.request_ok(Request::PostStats {
+            battery,
+            wifi_ssid: wifi_ssid.into(),
+            wifi_rssi,
+        })
+        .await
+    }
+
+    /// Send a text notification with the specified content, severity and category.
+    ///
+    /// # Errors
+    /// Generic I/O. If the content is longer than [`MAX_NOTIFICATION_LEN`],
+    /// `Err(Error::NotificationTooLong)` is returned.
+    pub async fn send_notification(
+        &mut self,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+    ) -> Result<()> {
+        if content.len() > MAX_NOTIFICATION_LEN {
+            return Err(Error::NotificationTooLong);
+        }
+
+        self.request_ok(Request::SendNotification {
+            content: content.into(),
+            severity,
+            category,
+        })
+        .await
+    }
+
+    /// Post the node's location.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub async fn post_location(
+        &mut self,
+        latitude: Latitude,
+        longitude: Longitude,
+        altitude: Option<Altitude>,
+    ) -> Result<()> {
+        self.request_ok(Request::PostLocation {
+            latitude,
+            longitude,
+            altitude,
+        })
+        .await
+    }
+
+    /// Returns the token of the current session, if the server supports sessions.
+    #[must_use]
+    pub const fn session(&self) -> Option<SessionToken> {
+        self.session
+    }
+
+    /// End the session by sending a `Bye` request and wait for the server to close the connection.
+    ///
+    /// # Errors
+    /// Generic I/O. If the server does not close the connection within the read timeout,
+    /// an I/O error is returned. The connection is closed in any case.
+    pub async fn close(mut self) -> Result<()> {
+        let raw = Message::Request(Request::Bye).to_raw();
+        write(&mut self.socket, &raw, &self.options).await?;
+
+        // The server closes the connection once it's done with the session.
+        let timeout = self.options.limit(self.options.read_timeout)?;
+        let mut buf = self.options.buffer();
+        let closed = async {
+            while self.socket.read(&mut buf).await? > 0 {}
+            Ok(())
+        };
+
+        Ok(with_timeout(timeout, closed).await?)
+    }
+
+    /// Reconnect to the server, resuming the current session if possible.
+    /// Returns whether the session was resumed.
+    async fn reconnect(&mut self) -> Result<bool> {
+        let connection =
+            Connection::open(&self.addrs, &self.mac, self.session, &self.options).await?;
+
+        self.socket = connection.socket;
+        self.session = connection.session;
+
+        Ok(connection.resumed)
+    }
+
+    async fn request<'b>(&mut self, req: Request, buf: &'b mut [u8]) -> Result<ResponseRef<'b>> {
+        let idempotent = is_idempotent(&req);
+        let raw = Message::Request(req).to_raw();
+        let mut retry = 0;
+
+        // See `PwmpClient::request()`.
+        let read = loop {
+            let why = match exchange(&mut self.socket, &raw, buf, &self.options).await {
+                Err(Error::Io(why)) => why,
+                result => break result?,
+            };
+
+            loop {
+                retry += 1;
+                if retry >= self.options.retry.max_attempts() {
+                    return Err(Error::Io(why));
+                }
+
+                self.options.backoff_async(retry).await?;
+                match self.reconnect().await {
+                    Ok(resumed) if !resumed && !idempotent => return Err(Error::Io(why)),
+                    Ok(_) => break,
+                    Err(Error::Io(_)) => (),
+                    Err(why) => return Err(why),
+                }
+            }
+        };
+
+        parse_response(&buf[..read])
+    }
+
+    async fn request_ok(&mut self, req: Request) -> Result<()> {
+        let mut buf = self.options.buffer();
+        expect_ok(self.request(req, &mut buf).await?)
+    }
+}
+
+/// A greeted connection to one of the server's addresses.
+struct Connection {
+    socket: TcpStream,
+    session: Option<SessionToken>,
+    resumed: bool,
+}
+
+impl Connection {
+    /// Connect to the first address that accepts the connection and greet the server,
+    /// resuming the specified session if possible.
+    async fn open(
+        addrs: &[SocketAddr],
+        mac: &Mac,
+        session: Option<SessionToken>,
+        options: &Options,
+    ) -> Result<Self> {
+        let mut last_error = Error::NoAddress;
+
+        for addr in addrs {
+            match Self::greet(*addr, mac, session, options).await {
+                Ok(connection) => return Ok(connection),
+                Err(Error::Io(why)) => last_error = Error::Io(why),
+                Err(why) => return Err(why),
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Send the `Resume` greeting.
+    async fn greet(
+        addr: SocketAddr,
+        mac: &Mac,
+        session: Option<SessionToken>,
+        options: &Options,
+    ) -> Result<Self> {
+        let timeout = options.limit(options.connect_timeout)?;
+        let mut socket = with_timeout(timeout, TcpStream::connect(addr)).await?;
+        socket.set_nodelay(true)?;
+
+        // Greeting with `Resume` even without a session tells the server sessions are supported.
+        let raw = Message::Request(Request::Resume {
+            mac: mac.clone(),
+            session: session.unwrap_or(NEW_SESSION),
+        })
+        .to_raw();
+        let mut buf = options.buffer();
+
+        let read = exchange(&mut socket, &raw, &mut buf, options).await?;
+        let (session, resumed) = expect_welcome(parse_response(&buf[..read])?)?;
+
+        Ok(Self {
+            socket,
+            session,
+            resumed,
+        })
+    }
+}
+
+async fn exchange(
+    socket: &mut TcpStream,
+    raw: &[u8],
+    buf: &mut [u8],
+    options: &Options,
+) -> Result<usize> {
+    write(socket, raw, options).await?;
+
+    let read = read(socket, buf, options).await?;
+    if read == 0 {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(read)
+}
+
+async fn write(socket: &mut TcpStream, raw: &[u8], options: &Options) -> Result<()> {
+    let timeout = options.limit(options.write_timeout)?;
+    Ok(with_timeout(timeout, socket.write_all(raw)).await?)
+}
+
+async fn read(socket: &mut TcpStream, buf: &mut [u8], options: &Options) -> Result<usize> {
+    let timeout = options.limit(options.read_timeout)?;
+    Ok(with_timeout(timeout, socket.read(buf)).await?)
+}
+
+async fn with_timeout<T>(
+    duration: Duration,
+    future: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    timeout(duration, future)
+        .await
+        .unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::TimedOut)))
+}
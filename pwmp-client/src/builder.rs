@@ -0,0 +1,298 @@
+#[cfg(feature = "std")]
+use crate::transport::TcpConnector;
+use crate::{
+    clock::Clock,
+    error::Error,
+    retry::RetryPolicy,
+    transport::{Connector, Established, Transport},
+    PwmpClient, Result,
+};
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::time::Duration;
+use pwmp_types::mac::Mac;
+#[cfg(feature = "std")]
+use std::net::ToSocketAddrs;
+
+const DEFAULT_BUFFER_SIZE: usize = 96;
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(4);
+const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Builder for a [`PwmpClient`] with custom timeouts and buffer sizes.
+///
+/// ```no_run
+/// # use pwmp_client::builder::PwmpClientBuilder;
+/// # use pwmp_types::mac::Mac;
+/// # use std::time::Duration;
+/// let client = PwmpClientBuilder::new(Mac::new(1, 2, 3, 4, 5, 6))
+///     .read_timeout(Duration::from_secs(2))
+///     .deadline(Duration::from_secs(10))
+///     .build("pwmp.example.com:55300")?;
+/// # Ok::<(), pwmp_client::error::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PwmpClientBuilder {
+    mac: Mac,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    deadline: Option<Duration>,
+    buffer_size: usize,
+    retry: RetryPolicy,
+    clock: Option<Clock>,
+}
+
+/// Options of a connected client.
+#[derive(Debug, Clone)]
+pub(crate) struct Options {
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub write_timeout: Duration,
+    pub deadline: Option<Duration>,
+    pub buffer_size: usize,
+    pub retry: RetryPolicy,
+    pub clock: Clock,
+}
+
+impl PwmpClientBuilder {
+    /// Create a builder for a node with the specified MAC address, using the default options.
+    #[must_use]
+    pub const fn new(mac: Mac) -> Self {
+        Self {
+            mac,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            deadline: None,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            retry: RetryPolicy::new(2),
+            clock: None,
+        }
+    }
+
+    /// Maximum time to wait for a connection to be established, per address.
+    #[must_use]
+    pub const fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Maximum time to wait for a response from the server.
+    #[must_use]
+    pub const fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Maximum time to wait for a request to be sent.
+    #[must_use]
+    pub const fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Maximum duration of the whole session, starting when connecting. Useful for nodes
+    /// that only have a limited energy budget per wakeup. Once it passes, all operations
+    /// fail with [`Error::DeadlineExceeded`].
+    #[must_use]
+    pub const fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Size of the buffer used to receive responses. Responses that don't fit
+    /// cannot be parsed.
+    #[must_use]
+    pub const fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// How to retry connecting to the server, and requests that failed because
+    /// the connection was lost.
+    #[must_use]
+    pub const fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Source of time for the deadline, the delay between retries and the
+    /// [`SessionReport`](crate::report::SessionReport). Defaults to the system clock with the
+    /// `std` feature. Without it, nodes should provide their own.
+    #[must_use]
+    pub const fn clock(mut self, clock: Clock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Connect to a PWMP server. Every address `addr` resolves to is tried in order,
+    /// until a connection is established.
+    ///
+    /// # Errors
+    /// If the server rejects the client, `Err(Error::Rejected)` is returned.
+    /// If no address could be connected to, the last connection error is returned.
+    #[cfg(feature = "std")]
+    pub fn build<A: ToSocketAddrs>(self, addr: A) -> Result<PwmpClient> {
+        let (mac, options) = self.into_parts();
+        let connector = TcpConnector::new(addr.to_socket_addrs()?.collect());
+
+        PwmpClient::with_options(Box::new(connector), mac, options)
+    }
+
+    /// Connect to the first of multiple PWMP servers that accepts the connection, for eg.
+    /// a primary and a standby server. Servers are tried in the specified order, both when
+    /// connecting and when reconnecting after the connection was lost. Use
+    /// [`PwmpClient::server_addr()`] to find out which server answered.
+    ///
+    /// # Errors
+    /// If the server rejects the client, `Err(Error::Rejected)` is returned.
+    /// If no server could be connected to, the last connection error is returned.
+    #[cfg(feature = "std")]
+    pub fn build_failover<A: ToSocketAddrs>(self, servers: &[A]) -> Result<PwmpClient> {
+        let mut addrs = Vec::new();
+        let mut last_error = None;
+
+        // A server that can't be resolved shouldn't prevent connecting to the others.
+        for server in servers {
+            match server.to_socket_addrs() {
+                Ok(resolved) => addrs.extend(resolved),
+                Err(why) => last_error = Some(why),
+            }
+        }
+
+        if let (true, Some(why)) = (addrs.is_empty(), last_error) {
+            return Err(why.into());
+        }
+
+        let (mac, options) = self.into_parts();
+        PwmpClient::with_options(Box::new(TcpConnector::new(addrs)), mac, options)
+    }
+
+    /// Connect to a PWMP server over TLS. Like with [`build()`](Self::build), every address
+    /// `addr` resolves to is tried in order. A server that presents an untrusted certificate
+    /// is treated like one that could not be connected to.
+    ///
+    /// # Errors
+    /// If the TLS options are invalid, `Err(Error::Tls)` is returned.
+    /// If the server rejects the client, `Err(Error::Rejected)` is returned.
+    /// If no address could be connected to, the last connection error is returned.
+    #[cfg(feature = "tls")]
+    pub fn build_tls<A: ToSocketAddrs>(
+        self,
+        addr: A,
+        tls: crate::tls::TlsOptions,
+    ) -> Result<PwmpClient<crate::tls::TlsStream>> {
+        let connector = tls.into_connector(addr.to_socket_addrs()?.collect())?;
+        let (mac, options) = self.into_parts();
+
+        PwmpClient::with_options(Box::new(connector), mac, options)
+    }
+
+    /// Communicate with the server over an already connected transport, for eg. a TLS stream
+    /// or a serial link. Since the client cannot open a new connection by itself, requests
+    /// that fail because the connection was lost are not retried.
+    ///
+    /// # Errors
+    /// If the server rejects the client, `Err(Error::Rejected)` is returned.
+    pub fn build_with_transport<T: Transport + Send + 'static>(
+        self,
+        transport: T,
+    ) -> Result<PwmpClient<T>> {
+        let (mac, options) = self.into_parts();
+        PwmpClient::with_options(Box::new(Established::new(transport)), mac, options)
+    }
+
+    /// Connect using a custom [`Connector`], which is also used to reconnect after the
+    /// connection was lost.
+    ///
+    /// # Errors
+    /// If the server rejects the client, `Err(Error::Rejected)` is returned.
+    /// If no endpoint could be connected to, the last connection error is returned.
+    pub fn build_with_connector<T, C>(self, connector: C) -> Result<PwmpClient<T>>
+    where
+        T: Transport,
+        C: Connector<T> + Send + 'static,
+    {
+        let (mac, options) = self.into_parts();
+        PwmpClient::with_options(Box::new(connector), mac, options)
+    }
+
+    /// Same as [`build()`](Self::build), but creates an
+    /// [`AsyncPwmpClient`](crate::async_client::AsyncPwmpClient).
+    ///
+    /// # Errors
+    /// See [`build()`](Self::build).
+    #[cfg(feature = "tokio")]
+    pub async fn build_async<A: tokio::net::ToSocketAddrs>(
+        self,
+        addr: A,
+    ) -> Result<crate::async_client::AsyncPwmpClient> {
+        let (mac, options) = self.into_parts();
+        let addrs = tokio::net::lookup_host(addr).await?;
+
+        crate::async_client::AsyncPwmpClient::with_options(addrs, mac, options).await
+    }
+
+    fn into_parts(self) -> (Mac, Options) {
+        let clock = self.clock.unwrap_or_default();
+        let options = Options {
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            deadline: self.deadline.map(|deadline| clock.now() + deadline),
+            buffer_size: self.buffer_size,
+            retry: self.retry,
+            clock,
+        };
+
+        (self.mac, options)
+    }
+}
+
+impl Options {
+    /// Limit the timeout of an operation to the time left until the deadline.
+    pub fn limit(&self, timeout: Duration) -> Result<Duration> {
+        let Some(deadline) = self.deadline else {
+            return Ok(timeout);
+        };
+
+        match deadline.checked_sub(self.clock.now()) {
+            Some(left) if !left.is_zero() => Ok(timeout.min(left)),
+            _ => Err(Error::DeadlineExceeded),
+        }
+    }
+
+    /// Wait before the specified retry.
+    pub fn backoff(&self, retry: u32) -> Result<()> {
+        self.clock.sleep(self.backoff_delay(retry)?);
+        Ok(())
+    }
+
+    /// Same as [`backoff()`](Self::backoff), but without blocking the thread.
+    #[cfg(feature = "tokio")]
+    pub async fn backoff_async(&self, retry: u32) -> Result<()> {
+        tokio::time::sleep(self.backoff_delay(retry)?).await;
+        Ok(())
+    }
+
+    fn backoff_delay(&self, retry: u32) -> Result<Duration> {
+        #[cfg(feature = "std")]
+        let delay = self.retry.delay(retry);
+        #[cfg(not(feature = "std"))]
+        let delay = self
+            .retry
+            .jittered(retry, crate::retry::random_from(self.clock.now()));
+
+        // There's no point in waiting if the deadline passes in the meantime.
+        if self.limit(delay)? < delay {
+            return Err(Error::DeadlineExceeded);
+        }
+
+        Ok(delay)
+    }
+
+    /// Returns a new receive buffer.
+    pub fn buffer(&self) -> Vec<u8> {
+        vec![0; self.buffer_size]
+    }
+}
@@ -0,0 +1,74 @@
+use core::time::Duration;
+
+/// Source of time for session deadlines, the delay between retries and the
+/// [`SessionReport`](crate::report::SessionReport).
+///
+/// With the `std` feature, the system clock is used by default. Without it, nodes should
+/// provide their own using [`PwmpClientBuilder::clock()`](crate::builder::PwmpClientBuilder::clock),
+/// otherwise deadlines never pass, retries are not delayed and all timings are zero.
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    now: fn() -> Duration,
+    sleep: fn(Duration),
+}
+
+impl Clock {
+    /// Create a clock from a function returning the time elapsed since a fixed point, for eg.
+    /// the boot of the node, and a function blocking for the specified duration.
+    #[must_use]
+    pub const fn new(now: fn() -> Duration, sleep: fn(Duration)) -> Self {
+        Self { now, sleep }
+    }
+
+    /// The system clock.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub const fn system() -> Self {
+        Self::new(system_now, std::thread::sleep)
+    }
+
+    /// A clock that never advances and doesn't block.
+    #[cfg(not(feature = "std"))]
+    const fn stopped() -> Self {
+        const fn now() -> Duration {
+            Duration::ZERO
+        }
+
+        const fn sleep(_duration: Duration) {}
+
+        Self::new(now, sleep)
+    }
+
+    /// Returns the current time.
+    pub(crate) fn now(&self) -> Duration {
+        (self.now)()
+    }
+
+    /// Returns the time elapsed since `earlier`.
+    pub(crate) fn elapsed(&self, earlier: Duration) -> Duration {
+        self.now().saturating_sub(earlier)
+    }
+
+    /// Block for the specified duration.
+    pub(crate) fn sleep(&self, duration: Duration) {
+        (self.sleep)(duration);
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        #[cfg(feature = "std")]
+        return Self::system();
+
+        #[cfg(not(feature = "std"))]
+        Self::stopped()
+    }
+}
+
+#[cfg(feature = "std")]
+fn system_now() -> Duration {
+    use std::{sync::OnceLock, time::Instant};
+
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed()
+}
@@ -0,0 +1,89 @@
+use pwmp_types::discovery::{Discovery, DISCOVERY_GROUP, DISCOVERY_PORT};
+use std::{
+    boxed::Box,
+    io,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+    vec::Vec,
+};
+
+/// A server that answered a discovery probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredServer {
+    /// Address to connect to. This is the address the announcement came from, with the port
+    /// announced by the server.
+    pub addr: SocketAddr,
+    /// Host name announced by the server, if it has one.
+    pub host: Option<Box<str>>,
+    /// Protocol version of the server, see
+    /// [`PROTOCOL_VERSION`](pwmp_types::discovery::PROTOCOL_VERSION).
+    pub version: u16,
+    /// Whether the server requires TLS.
+    pub tls: bool,
+}
+
+/// Broadcast a probe on the local network, and to the discovery multicast group, and collect
+/// the answers until the timeout expires.
+pub(crate) fn discover(timeout: Duration) -> io::Result<Vec<DiscoveredServer>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    socket.set_multicast_ttl_v4(1)?;
+
+    let probe = Discovery::Probe.to_raw();
+    let broadcast = socket.send_to(&probe, (Ipv4Addr::BROADCAST, DISCOVERY_PORT));
+    let multicast = socket.send_to(&probe, (DISCOVERY_GROUP, DISCOVERY_PORT));
+
+    // One of them is enough, for eg. networks without multicast routes.
+    if let (Err(why), Err(_)) = (broadcast, multicast) {
+        return Err(why);
+    }
+
+    let until = Instant::now() + timeout;
+    let mut servers = Vec::new();
+    let mut buf = [0; 256];
+
+    loop {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+
+        socket.set_read_timeout(Some(left))?;
+        let (read, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(why) if is_timeout(&why) => break,
+            Err(why) => return Err(why),
+        };
+
+        let Some(Discovery::Announce {
+            host,
+            port,
+            version,
+            tls,
+        }) = Discovery::from_raw(&buf[..read])
+        else {
+            continue;
+        };
+
+        let server = DiscoveredServer {
+            addr: SocketAddr::new(from.ip(), port),
+            host,
+            version,
+            tls,
+        };
+
+        // Servers answer both probes.
+        if !servers.contains(&server) {
+            servers.push(server);
+        }
+    }
+
+    Ok(servers)
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
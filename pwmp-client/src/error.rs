@@ -1,14 +1,24 @@
-use std::io;
+/// I/O error. Without the `std` feature, only it's [`ErrorKind`](embedded_io::ErrorKind) is kept.
+#[cfg(feature = "std")]
+pub type IoError = std::io::Error;
+
+/// I/O error. With the `std` feature, it's a [`std::io::Error`](https://doc.rust-lang.org/std/io/struct.Error.html).
+#[cfg(not(feature = "std"))]
+pub type IoError = embedded_io::ErrorKind;
 
 /// Errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// Generic I/O error.
-    #[error("I/O error: {0}")]
-    Io(#[from] io::Error),
+    #[cfg_attr(feature = "std", error("I/O error: {0}"))]
+    #[cfg_attr(not(feature = "std"), error("I/O error: {0:?}"))]
+    Io(#[cfg_attr(feature = "std", from)] IoError),
     /// Server rejected the client.
     #[error("server rejected")]
     Rejected,
+    /// Server postponed the request, it should be sent again later.
+    #[error("server postponed")]
+    Postponed,
     /// Expected a response message, got request instead.
     #[error("not response")]
     NotResponse,
@@ -21,4 +31,44 @@ pub enum Error {
     /// Malformed response
     #[error("malformed response")]
     MalformedResponse,
+    /// The address did not resolve to any socket addresses.
+    #[error("no address to connect to")]
+    NoAddress,
+    /// The session deadline has passed.
+    #[error("session deadline exceeded")]
+    DeadlineExceeded,
+    /// Notification content is longer than [`MAX_NOTIFICATION_LEN`](pwmp_types::notification::MAX_NOTIFICATION_LEN).
+    #[error("notification too long")]
+    NotificationTooLong,
+    /// Invalid TLS configuration.
+    #[cfg(feature = "tls")]
+    #[error("TLS: {0}")]
+    Tls(#[from] rustls::Error),
+}
+
+// `ErrorKind` doesn't implement `core::error::Error`, so it can't be a source.
+#[cfg(not(feature = "std"))]
+impl From<IoError> for Error {
+    fn from(kind: IoError) -> Self {
+        Self::Io(kind)
+    }
+}
+
+/// Create an I/O error of the specified kind.
+pub(crate) fn io_error(kind: embedded_io::ErrorKind) -> IoError {
+    #[cfg(feature = "std")]
+    return std::io::ErrorKind::from(kind).into();
+
+    #[cfg(not(feature = "std"))]
+    kind
+}
+
+/// Error returned when the server closed the connection while a response was expected.
+pub(crate) fn unexpected_eof() -> IoError {
+    #[cfg(feature = "std")]
+    return std::io::ErrorKind::UnexpectedEof.into();
+
+    // There's no such kind in `embedded_io`.
+    #[cfg(not(feature = "std"))]
+    embedded_io::ErrorKind::ConnectionAborted
 }
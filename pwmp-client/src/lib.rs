@@ -1,100 +1,243 @@
+#![no_std]
 #![allow(clippy::missing_panics_doc, clippy::missing_errors_doc)]
-use error::Error;
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::boxed::Box;
+use builder::Options;
+use embedded_io::ErrorKind;
+use error::{io_error, Error, IoError};
+use outbox::{Outbox, QueuedMeasurement, QueuedStats};
 pub use pwmp_types;
 use pwmp_types::{
-    aliases::{AirPressure, BatteryVoltage, Humidity, Rssi, Temperature},
+    aliases::{
+        AirPressure, Altitude, BatteryVoltage, Humidity, Latitude, Longitude, Rssi, SessionToken,
+        Temperature,
+    },
+    borrowed::{MessageRef, ResponseRef},
     mac::Mac,
     multitype::SettingValue,
-    request::Request,
-    response::Response,
+    notification::{NotificationCategory, NotificationSeverity, MAX_NOTIFICATION_LEN},
+    request::{Request, NEW_SESSION},
     setting::SettingName,
     Message,
 };
+use relay::RelayedNode;
+use report::{request_name, SessionReport};
+use settings::{NodeSettings, SettingsMap};
+#[cfg(feature = "std")]
 use std::{
-    io::{Read, Write},
-    net::{TcpStream, ToSocketAddrs},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
     time::Duration,
+    vec::Vec,
 };
-
-const RCV_BUFFER_SIZE: usize = 96;
-const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
-const READ_TIMEOUT: Duration = Duration::from_secs(4);
-const WRITE_TIMEOUT: Duration = Duration::from_secs(4);
-type Result<T> = ::std::result::Result<T, Error>;
-
+#[cfg(feature = "tracing")]
+use tracing::Span;
+#[cfg(feature = "std")]
+use transport::FromStd;
+use transport::{Connector, Transport};
+
+type Result<T> = ::core::result::Result<T, Error>;
+
+/// Contains the [`AsyncPwmpClient`](async_client::AsyncPwmpClient) type.
+#[cfg(feature = "tokio")]
+pub mod async_client;
+/// Contains the [`PwmpClientBuilder`](builder::PwmpClientBuilder) type.
+pub mod builder;
+/// Contains the [`Clock`](clock::Clock) type.
+pub mod clock;
+/// Contains the [`DiscoveredServer`](discovery::DiscoveredServer) type.
+#[cfg(feature = "std")]
+pub mod discovery;
 /// Contains the [`Error`] type.
 pub mod error;
+/// Contains the [`MockServer`](mock::MockServer) for testing clients.
+#[cfg(feature = "mock")]
+pub mod mock;
+/// Contains the [`Outbox`] type.
+pub mod outbox;
+/// Contains the [`RelayedNode`] type.
+pub mod relay;
+/// Contains the [`SessionReport`] type.
+pub mod report;
+/// Contains the [`RetryPolicy`](retry::RetryPolicy) type.
+pub mod retry;
+/// Contains the typestate [`Session`](session::Session) API.
+pub mod session;
+/// Contains the [`NodeSettings`] type.
+pub mod settings;
+/// Contains [`TlsOptions`](tls::TlsOptions) for connecting over TLS.
+#[cfg(feature = "tls")]
+pub mod tls;
+/// Contains the [`Transport`] trait.
+pub mod transport;
 
 #[allow(clippy::doc_markdown)]
 /// PixelWeather Messaging Protocol Client.
-pub struct PwmpClient(TcpStream);
+///
+/// If the connection is lost during a session, the client will transparently reconnect,
+/// resume the session and retry the failed request, as described by it's
+/// [`RetryPolicy`](retry::RetryPolicy).
+///
+/// The session should be ended using [`close()`](Self::close). If the client is dropped instead,
+/// it tries to end the session the same way, but gives up after the read timeout and
+/// ignores any errors.
+///
+/// By default, the client communicates over TCP. Other transports, such as TLS streams, Unix
+/// sockets or the sockets of a microcontroller's networking stack, can be used with
+/// [`PwmpClientBuilder::build_with_transport()`](builder::PwmpClientBuilder::build_with_transport)
+/// and [`PwmpClientBuilder::build_with_connector()`](builder::PwmpClientBuilder::build_with_connector).
+pub struct PwmpClient<
+    #[cfg(feature = "std")] T: Transport = FromStd<TcpStream>,
+    #[cfg(not(feature = "std"))] T: Transport,
+> {
+    socket: T,
+    connector: Box<dyn Connector<T> + Send>,
+    mac: Mac,
+    session: Option<SessionToken>,
+    options: Options,
+    closed: bool,
+    outbox: Option<Outbox>,
+    /// Whether the measurement of this session was queued in the outbox.
+    queued: bool,
+    /// Whether the last request was fully sent at least once, so the server may have
+    /// processed it even if no response arrived.
+    sent: bool,
+    report: SessionReport,
+    #[cfg(feature = "tracing")]
+    span: Span,
+}
 
+#[cfg(feature = "std")]
 impl PwmpClient {
-    /// Create a new client by connecting to a PWMP server.
+    /// Create a new client by connecting to a PWMP server using the default options.
+    /// Use [`builder()`](Self::builder) to customize them.
     ///
     /// # Errors
     /// If the server rejects the client (for eg. if it's unathorized)
     /// an `Err(Error::Reject)` is returned. An error is also returned
     /// if a generic I/O error occurred.
     pub fn new<A: ToSocketAddrs>(addr: A, mac: Mac) -> Result<Self> {
-        let addr = addr.to_socket_addrs()?.next().unwrap();
-        let socket = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+        builder::PwmpClientBuilder::new(mac).build(addr)
+    }
+
+    /// Create a [`PwmpClientBuilder`](builder::PwmpClientBuilder) for a node with the
+    /// specified MAC address.
+    #[must_use]
+    pub const fn builder(mac: Mac) -> builder::PwmpClientBuilder {
+        builder::PwmpClientBuilder::new(mac)
+    }
+
+    /// Find PWMP servers on the local network, by broadcasting a probe and waiting for answers
+    /// until the timeout expires. Servers only answer if their discovery responder is enabled.
+    ///
+    /// Servers speaking an incompatible protocol version are returned as well,
+    /// check [`DiscoveredServer::version`](discovery::DiscoveredServer::version).
+    ///
+    /// # Errors
+    /// If the probe could not be sent.
+    pub fn discover(timeout: Duration) -> Result<Vec<discovery::DiscoveredServer>> {
+        Ok(discovery::discover(timeout)?)
+    }
 
-        socket.set_read_timeout(Some(READ_TIMEOUT))?;
-        socket.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    /// Returns the address of the server that answered. If the client was built using
+    /// [`PwmpClientBuilder::build_failover()`](builder::PwmpClientBuilder::build_failover),
+    /// this tells which of the servers is being used.
+    #[must_use]
+    pub fn server_addr(&self) -> Option<SocketAddr> {
+        self.socket.0.peer_addr().ok()
+    }
+}
 
-        let mut client = Self(socket);
-        client.send_greeting(mac)?;
+impl<T: Transport> PwmpClient<T> {
+    pub(crate) fn with_options(
+        mut connector: Box<dyn Connector<T> + Send>,
+        mac: Mac,
+        options: Options,
+    ) -> Result<Self> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("pwmp_session", mac = %mac);
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let mut report = SessionReport::default();
+        let mut retry = 0;
+
+        let connection = loop {
+            match Connection::open(&mut *connector, &mac, None, &options, &mut report) {
+                Err(Error::Io(_))
+                    if retry + 1 < options.retry.max_attempts() && connector.can_reconnect() =>
+                {
+                    retry += 1;
+                    options.backoff(retry)?;
+                }
+                result => break result?,
+            }
+        };
 
-        Ok(client)
+        Ok(Self {
+            socket: connection.socket,
+            connector,
+            mac,
+            session: connection.session,
+            options,
+            closed: false,
+            outbox: None,
+            queued: false,
+            sent: false,
+            report,
+            #[cfg(feature = "tracing")]
+            span: span.clone(),
+        })
     }
 
     /// Try to ping the server. Returns whether the server responded correctly.
     /// On an I/O error, `false` is returned.
     pub fn ping(&mut self) -> bool {
-        if self.send_request(Request::Ping).is_err() {
-            return false;
-        }
-
-        let Ok(response) = self.await_response() else {
+        let mut buf = self.options.buffer();
+        let Ok(response) = self.request(Request::Ping, &mut buf) else {
             return false;
         };
 
-        response == Response::Pong
+        response == ResponseRef::Pong
     }
 
     /// Get values of multiple settings.
     ///
     /// # Errors
     /// Generic I/O.
-    #[allow(clippy::items_after_statements)]
     pub fn get_settings<const N: usize>(
         &mut self,
         settings: [SettingName; N],
     ) -> Result<[SettingValue; N]> {
-        self.send_request(Request::GetSettings(settings.into()))?;
-        let response = self.await_response()?;
-
-        let Response::Settings(values) = response else {
-            return Err(Error::UnexpectedVariant);
-        };
-
-        if values.len() != N {
-            return Err(Error::MalformedResponse);
-        }
-
-        const ARRAY_REPEAT_VALUE: SettingValue = SettingValue::Number(0);
-        let mut array = [ARRAY_REPEAT_VALUE; N];
+        self.settings_request(Request::GetSettings(settings.into()))
+    }
 
-        for (i, value) in values.iter().cloned().enumerate() {
-            array[i] = value;
-        }
+    /// Get values of multiple settings, without knowing their amount at compile time.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn get_settings_dyn(&mut self, settings: &[SettingName]) -> Result<SettingsMap> {
+        self.settings_map_request(Request::GetSettings(settings.into()), settings)
+    }
 
-        Ok(array)
+    /// Get all settings of the node. Missing values are replaced with their defaults.
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn fetch_node_settings(&mut self) -> Result<NodeSettings> {
+        let values = self.get_settings_dyn(&NodeSettings::NAMES)?;
+        Ok(NodeSettings::from_values(&values))
     }
 
     /// Post node measurements.
     ///
+    /// If an outbox is attached and the measurements were not sent to the server, they are
+    /// queued in the outbox. The error is returned anyway, even if queueing failed. Measurements that were sent but
+    /// not answered are not queued, since the server may have stored them already. Without
+    /// the `std` feature, measurements are never queued automatically.
+    ///
     /// # Errors
     /// Generic I/O.
     pub fn post_measurements(
@@ -103,18 +246,33 @@ impl PwmpClient {
         humidity: Humidity,
         air_pressure: Option<AirPressure>,
     ) -> Result<()> {
-        self.send_request(Request::PostResults {
+        let result = self.request_ok(Request::PostResults {
             temperature,
             humidity,
             air_pressure,
-        })?;
-        self.await_ok()?;
+        });
+
+        // Without the system clock, there's no time to queue the measurement with.
+        #[cfg(feature = "std")]
+        if let (Err(why), Some(outbox)) = (&result, &mut self.outbox) {
+            if is_delivery_failure(why) && !self.sent {
+                let measurement = QueuedMeasurement::now(temperature, humidity, air_pressure);
+                self.queued = outbox.push(measurement).unwrap_or_else(|why| {
+                    queue_failed(&why);
+                    false
+                });
+            }
+        }
 
-        Ok(())
+        result
     }
 
     /// Post node stats.
     ///
+    /// If the measurements of this session were queued in the outbox and the stats could not
+    /// be delivered either, they are queued along with them. The error is returned anyway, even
+    /// if queueing failed.
+    ///
     /// # Errors
     /// Generic I/O.
     pub fn post_stats(
@@ -123,70 +281,522 @@ impl PwmpClient {
         wifi_ssid: &str,
         wifi_rssi: Rssi,
     ) -> Result<()> {
-        self.send_request(Request::PostStats {
+        let result = self.request_ok(Request::PostStats {
             battery,
             wifi_ssid: wifi_ssid.into(),
             wifi_rssi,
+        });
+
+        if let (Err(why), Some(outbox), true) = (&result, &mut self.outbox, self.queued) {
+            if is_delivery_failure(why) {
+                let stats = QueuedStats {
+                    battery,
+                    wifi_ssid: wifi_ssid.into(),
+                    wifi_rssi,
+                };
+
+                if let Err(why) = outbox.attach_stats(stats) {
+                    queue_failed(&why);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Attach an [`Outbox`] and post all measurements queued in it. Returns the number of
+    /// posted measurements.
+    ///
+    /// While the outbox is attached, measurements that could not be delivered are queued in it.
+    ///
+    /// # Errors
+    /// Generic I/O. Measurements that were not posted stay in the outbox.
+    pub fn attach_outbox(&mut self, outbox: Outbox) -> Result<usize> {
+        self.outbox = Some(outbox);
+        self.flush_outbox()
+    }
+
+    /// Detach the outbox from the client.
+    pub fn detach_outbox(&mut self) -> Option<Outbox> {
+        self.outbox.take()
+    }
+
+    /// Post all measurements queued in the attached outbox. Returns the number of posted
+    /// measurements. Measurements postponed by the server stay in the outbox, so they can be
+    /// posted again later, for eg. once the node's clock is corrected. Measurements it rejected,
+    /// for eg. because they are out of range, are dropped.
+    ///
+    /// # Errors
+    /// Generic I/O. Measurements that were not posted stay in the outbox.
+    pub fn flush_outbox(&mut self) -> Result<usize> {
+        let mut posted = 0;
+        let mut index = 0;
+
+        while let Some(req) = self
+            .outbox
+            .as_ref()
+            .and_then(|outbox| outbox.entries().get(index))
+            .map(QueuedMeasurement::to_request)
+        {
+            match self.request_ok(req) {
+                Ok(()) => posted += 1,
+                Err(Error::Rejected) => (),
+                Err(Error::Postponed) => {
+                    index += 1;
+                    continue;
+                }
+                Err(why) => return Err(why),
+            }
+
+            if let Some(outbox) = &mut self.outbox {
+                outbox.remove(index)?;
+            }
+        }
+
+        Ok(posted)
+    }
+
+    /// Send a text notification with the specified content, severity and category.
+    ///
+    /// # Errors
+    /// Generic I/O. If the content is longer than [`MAX_NOTIFICATION_LEN`],
+    /// `Err(Error::NotificationTooLong)` is returned.
+    pub fn send_notification(
+        &mut self,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+    ) -> Result<()> {
+        if content.len() > MAX_NOTIFICATION_LEN {
+            return Err(Error::NotificationTooLong);
+        }
+
+        self.request_ok(Request::SendNotification {
+            content: content.into(),
+            severity,
+            category,
         })?;
-        self.await_ok()?;
 
         Ok(())
     }
 
-    /// Send a text notification with the specified content.
+    /// Post the node's location.
     ///
     /// # Errors
     /// Generic I/O.
-    pub fn send_notification(&mut self, content: &str) -> Result<()> {
-        assert!(content.len() <= 64, "Message content too large");
-        self.send_request(Request::SendNotification(content.into()))?;
-        self.await_ok()?;
+    pub fn post_location(
+        &mut self,
+        latitude: Latitude,
+        longitude: Longitude,
+        altitude: Option<Altitude>,
+    ) -> Result<()> {
+        self.request_ok(Request::PostLocation {
+            latitude,
+            longitude,
+            altitude,
+        })?;
 
         Ok(())
     }
 
-    fn send_greeting(&mut self, mac: Mac) -> Result<()> {
-        self.send_request(Request::Hello { mac })?;
-        self.await_ok()
+    /// Perform requests on behalf of another node, for eg. a sensor that is connected to this
+    /// node over LoRa or BLE. The node must be assigned to this gateway on the server.
+    pub fn relay(&mut self, child: Mac) -> RelayedNode<'_, T> {
+        RelayedNode::new(self, child)
     }
 
-    fn send_request(&mut self, req: Request) -> Result<()> {
-        self.0.write_all(&Message::Request(req).to_raw())?;
-        self.0.flush()?;
+    /// Returns the token of the current session, if the server supports sessions.
+    #[must_use]
+    pub const fn session(&self) -> Option<SessionToken> {
+        self.session
+    }
 
-        Ok(())
+    /// Turn the client into a [`Session`](session::Session), which enforces the order
+    /// of requests at compile time.
+    #[must_use]
+    pub const fn into_session(self) -> session::Session<session::Connected, T> {
+        session::Session::start(self)
+    }
+
+    /// Returns the timing and traffic of the session so far.
+    #[must_use]
+    pub const fn report(&self) -> &SessionReport {
+        &self.report
+    }
+
+    /// End the session by sending a `Bye` request and wait for the server to close the connection.
+    ///
+    /// # Errors
+    /// Generic I/O. If the server does not close the connection within the read timeout,
+    /// an I/O error is returned. The connection is closed in any case.
+    pub fn close(mut self) -> Result<()> {
+        self.end_session()
+    }
+
+    /// End the session like [`close()`](Self::close) and return the final [`SessionReport`],
+    /// which is returned even if the session could not be ended cleanly.
+    pub fn close_with_report(mut self) -> (Result<()>, SessionReport) {
+        let result = self.end_session();
+        (result, core::mem::take(&mut self.report))
+    }
+
+    fn end_session(&mut self) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        let _entered = self.span.clone().entered();
+
+        self.closed = true;
+        let result = self.wait_for_close();
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            connections = self.report.connections,
+            connect_time = ?self.report.connect_time,
+            handshake_time = ?self.report.handshake_time,
+            requests = self.report.requests.len(),
+            request_time = ?self.report.request_time(),
+            bytes_sent = self.report.bytes_sent,
+            bytes_received = self.report.bytes_received,
+            "session ended"
+        );
+
+        result
+    }
+
+    /// Send a `Bye` request and wait for the server to close the connection.
+    fn wait_for_close(&mut self) -> Result<()> {
+        self.send_request(Request::Bye)?;
+
+        // The server closes the connection once it's done with the session.
+        let clock = self.options.clock;
+        let until = clock.now() + self.options.limit(self.options.read_timeout)?;
+        let mut buf = self.options.buffer();
+
+        loop {
+            let left = until.saturating_sub(clock.now());
+            if left.is_zero() {
+                return Err(io_error(ErrorKind::TimedOut).into());
+            }
+
+            self.socket
+                .set_read_timeout(left)
+                .map_err(T::into_io_error)?;
+            let read = self.socket.read(&mut buf).map_err(T::into_io_error)?;
+            if read == 0 {
+                return Ok(());
+            }
+
+            self.report.bytes_received += read as u64;
+        }
     }
 
-    fn await_response(&mut self) -> Result<Response> {
-        let mut buf = [0; RCV_BUFFER_SIZE];
-        let read = self.0.read(&mut buf)?;
+    /// Reconnect to the first available server and try to resume the current session.
+    /// Returns whether the session was resumed.
+    fn reconnect(&mut self) -> Result<bool> {
+        let connection = Connection::open(
+            &mut *self.connector,
+            &self.mac,
+            self.session,
+            &self.options,
+            &mut self.report,
+        )?;
+
+        self.socket = connection.socket;
+        self.session = connection.session;
+
+        Ok(connection.resumed)
+    }
+
+    fn request<'b>(&mut self, req: Request, buf: &'b mut [u8]) -> Result<ResponseRef<'b>> {
+        let idempotent = is_idempotent(&req);
+        let name = request_name(&req);
+        let raw = Message::Request(req).to_raw();
+        let mut retry = 0;
+        self.sent = false;
+
+        #[cfg(feature = "tracing")]
+        let _entered =
+            tracing::debug_span!(parent: &self.span, "pwmp_request", request = name).entered();
+
+        let read = loop {
+            let started = self.options.clock.now();
+            let result = self.exchange(&raw, buf);
+            let elapsed = self.options.clock.elapsed(started);
+            self.report.record(name, elapsed, result.is_ok());
+
+            let why = match result {
+                Err(Error::Io(why)) => why,
+                result => break result?,
+            };
+
+            loop {
+                retry += 1;
+                if retry >= self.options.retry.max_attempts() || !self.connector.can_reconnect() {
+                    return Err(Error::Io(why));
+                }
+
+                self.options.backoff(retry)?;
+                match self.reconnect() {
+                    // Requests that change state can only be retried if the server still knows
+                    // about them, otherwise they could be processed twice.
+                    Ok(resumed) if !resumed && !idempotent => return Err(Error::Io(why)),
+                    Ok(_) => break,
+                    Err(Error::Io(_)) => (),
+                    Err(why) => return Err(why),
+                }
+            }
+        };
+
+        parse_response(&buf[..read])
+    }
+
+    fn settings_request<const N: usize>(&mut self, req: Request) -> Result<[SettingValue; N]> {
+        let mut buf = self.options.buffer();
+        expect_settings(self.request(req, &mut buf)?)
+    }
+
+    fn settings_map_request(&mut self, req: Request, names: &[SettingName]) -> Result<SettingsMap> {
+        let mut buf = self.options.buffer();
+        expect_settings_map(self.request(req, &mut buf)?, names)
+    }
 
-        let message = Message::from_raw(&buf[..read]).ok_or(Error::MessageParse)?;
-        message.to_response().ok_or(Error::NotResponse)
+    fn request_ok(&mut self, req: Request) -> Result<()> {
+        let mut buf = self.options.buffer();
+        expect_ok(self.request(req, &mut buf)?)
     }
 
-    fn await_ok(&mut self) -> Result<()> {
-        let response = self.await_response()?;
+    fn exchange(&mut self, raw: &[u8], buf: &mut [u8]) -> Result<usize> {
+        self.write(raw)?;
+        self.sent = true;
 
-        match response {
-            Response::Ok => Ok(()),
-            Response::Reject => Err(Error::Rejected),
-            _ => Err(Error::NotResponse),
+        let read = self.read(buf)?;
+        if read == 0 {
+            return Err(error::unexpected_eof().into());
         }
+
+        Ok(read)
     }
 
-    fn connected(&self) -> bool {
-        if let Ok(amount) = self.0.peek(&mut []) {
-            return amount > 0;
+    fn send_request(&mut self, req: Request) -> Result<()> {
+        self.write(&Message::Request(req).to_raw())
+    }
+
+    fn write(&mut self, raw: &[u8]) -> Result<()> {
+        write(&mut self.socket, raw, &self.options, &mut self.report)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        read(&mut self.socket, buf, &self.options, &mut self.report)
+    }
+}
+
+/// A greeted connection to a server.
+struct Connection<T> {
+    socket: T,
+    session: Option<SessionToken>,
+    resumed: bool,
+}
+
+impl<T: Transport> Connection<T> {
+    /// Connect to the first endpoint that accepts the connection, and greet it. If a session
+    /// is specified, the server is asked to resume it.
+    fn open(
+        connector: &mut dyn Connector<T>,
+        mac: &Mac,
+        session: Option<SessionToken>,
+        options: &Options,
+        report: &mut SessionReport,
+    ) -> Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _entered = tracing::debug_span!("pwmp_connect", resume = session.is_some()).entered();
+
+        let mut last_error = Error::NoAddress;
+
+        for endpoint in 0..connector.endpoints() {
+            // Greeting with `Resume` even without a session tells the server sessions are supported.
+            let greeting = Request::Resume {
+                mac: mac.clone(),
+                session: session.unwrap_or(NEW_SESSION),
+            };
+
+            match Self::greet(connector, endpoint, greeting, options, report) {
+                Ok(connection) => return Ok(connection),
+                Err(Error::Io(why)) => last_error = Error::Io(why),
+                Err(why) => return Err(why),
+            }
         }
 
-        false
+        Err(last_error)
+    }
+
+    /// Send the `Resume` greeting.
+    fn greet(
+        connector: &mut dyn Connector<T>,
+        endpoint: usize,
+        req: Request,
+        options: &Options,
+        report: &mut SessionReport,
+    ) -> Result<Self> {
+        let timeout = options.limit(options.connect_timeout)?;
+
+        let clock = options.clock;
+        let started = clock.now();
+        let socket = connector.connect(endpoint, timeout);
+        report.connect_time += clock.elapsed(started);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(endpoint, elapsed = ?clock.elapsed(started), ok = socket.is_ok(), "connected");
+
+        let mut socket = socket?;
+        report.connections += 1;
+
+        let started = clock.now();
+        let welcome = Self::exchange_greeting(&mut socket, req, options, report);
+        report.handshake_time += clock.elapsed(started);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(elapsed = ?clock.elapsed(started), ok = welcome.is_ok(), "greeted");
+
+        let (session, resumed) = welcome?;
+
+        Ok(Self {
+            socket,
+            session,
+            resumed,
+        })
     }
+
+    /// Send the greeting and interpret the response.
+    fn exchange_greeting(
+        socket: &mut T,
+        req: Request,
+        options: &Options,
+        report: &mut SessionReport,
+    ) -> Result<(Option<SessionToken>, bool)> {
+        write(socket, &Message::Request(req).to_raw(), options, report)?;
+
+        let mut buf = options.buffer();
+        let read = read(socket, &mut buf, options, report)?;
+        expect_welcome(parse_response(&buf[..read])?)
+    }
+}
+
+fn write<T: Transport>(
+    socket: &mut T,
+    raw: &[u8],
+    options: &Options,
+    report: &mut SessionReport,
+) -> Result<()> {
+    let timeout = options.limit(options.write_timeout)?;
+    socket
+        .set_write_timeout(timeout)
+        .map_err(T::into_io_error)?;
+
+    socket.write_all(raw).map_err(T::into_io_error)?;
+    socket.flush().map_err(T::into_io_error)?;
+    report.bytes_sent += raw.len() as u64;
+
+    Ok(())
 }
 
-impl Drop for PwmpClient {
+fn read<T: Transport>(
+    socket: &mut T,
+    buf: &mut [u8],
+    options: &Options,
+    report: &mut SessionReport,
+) -> Result<usize> {
+    let timeout = options.limit(options.read_timeout)?;
+    socket.set_read_timeout(timeout).map_err(T::into_io_error)?;
+
+    let read = socket.read(buf).map_err(T::into_io_error)?;
+    report.bytes_received += read as u64;
+
+    Ok(read)
+}
+
+/// Log a failure to write to the outbox. The delivery error is returned instead, since
+/// it's more useful to the caller.
+fn queue_failed(error: &IoError) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(error = ?error, "failed to write to the outbox");
+
+    #[cfg(not(feature = "tracing"))]
+    let _ = error;
+}
+
+const fn is_delivery_failure(error: &Error) -> bool {
+    matches!(error, Error::Io(_) | Error::DeadlineExceeded)
+}
+
+fn is_idempotent(req: &Request) -> bool {
+    match req {
+        // The server ignores queued measurements it already stored.
+        Request::Ping | Request::GetSettings(..) | Request::PostQueued { .. } => true,
+        Request::Relay { request, .. } => is_idempotent(request),
+        _ => false,
+    }
+}
+
+fn parse_response(raw: &[u8]) -> Result<ResponseRef<'_>> {
+    let message = MessageRef::from_raw(raw).ok_or(Error::MessageParse)?;
+    message.to_response().ok_or(Error::NotResponse)
+}
+
+/// Interpret the response to a `Hello` or `Resume` request.
+/// Returns the session token, if any, and whether a previous session was resumed.
+fn expect_welcome(response: ResponseRef) -> Result<(Option<SessionToken>, bool)> {
+    match response {
+        ResponseRef::Welcome { session, resumed } => Ok((Some(session), resumed)),
+        ResponseRef::Ok => Ok((None, false)),
+        ResponseRef::Reject => Err(Error::Rejected),
+        _ => Err(Error::UnexpectedVariant),
+    }
+}
+
+fn expect_ok(response: ResponseRef) -> Result<()> {
+    match response {
+        ResponseRef::Ok => Ok(()),
+        ResponseRef::Reject => Err(Error::Rejected),
+        ResponseRef::Postponed => Err(Error::Postponed),
+        _ => Err(Error::NotResponse),
+    }
+}
+
+#[allow(clippy::items_after_statements)]
+fn expect_settings<const N: usize>(response: ResponseRef) -> Result<[SettingValue; N]> {
+    let ResponseRef::Settings(values) = response else {
+        return Err(Error::UnexpectedVariant);
+    };
+
+    if values.len() != N {
+        return Err(Error::MalformedResponse);
+    }
+
+    const ARRAY_REPEAT_VALUE: SettingValue = SettingValue::Number(0);
+    let mut array = [ARRAY_REPEAT_VALUE; N];
+
+    for (slot, value) in array.iter_mut().zip(values.iter()) {
+        *slot = value;
+    }
+
+    Ok(array)
+}
+
+fn expect_settings_map(response: ResponseRef, names: &[SettingName]) -> Result<SettingsMap> {
+    let ResponseRef::Settings(values) = response else {
+        return Err(Error::UnexpectedVariant);
+    };
+
+    if values.len() != names.len() {
+        return Err(Error::MalformedResponse);
+    }
+
+    Ok(names.iter().copied().zip(values.iter()).collect())
+}
+
+impl<T: Transport> Drop for PwmpClient<T> {
     fn drop(&mut self) {
-        let _ = self.send_request(Request::Bye);
-        while self.connected() {}
+        if !self.closed {
+            let _ = self.end_session();
+        }
     }
 }
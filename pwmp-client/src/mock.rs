@@ -0,0 +1,398 @@
+use crate::settings::SettingsMap;
+use pwmp_types::{
+    aliases::SessionToken, mac::Mac, multitype::SettingValue, request::Request, response::Response,
+    setting::SettingName, Message,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+    vec::Vec,
+};
+
+/// Bytes sent by [`Fault::Garbage`]. The message variant tag is out of range.
+const GARBAGE: [u8; 4] = [0xFF; 4];
+
+/// An in-process PWMP server for testing clients without a database.
+///
+/// It listens on an ephemeral port on the loopback interface and accepts every node, unless
+/// told otherwise. Every received request is recorded, so tests can check what the client sent.
+///
+/// Like the real server, it closes the connection when requests arrive out of order, for eg.
+/// statistics without a measurement, and replays the last response to a retried request after a
+/// session is resumed. Use [`MockServer::set_strict()`] to answer requests in any order.
+///
+/// ```
+/// # use pwmp_client::{mock::MockServer, PwmpClient};
+/// # use pwmp_types::{mac::Mac, request::Request};
+/// let server = MockServer::start()?;
+///
+/// let mut client = PwmpClient::new(server.addr(), Mac::new(1, 2, 3, 4, 5, 6))?;
+/// assert!(client.ping());
+/// client.close()?;
+///
+/// assert!(server.requests().contains(&Request::Ping));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+/// A misbehavior of the [`MockServer`], injected using [`MockServer::inject()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Respond with `Reject`.
+    Reject,
+    /// Respond with `Postponed`.
+    Postpone,
+    /// Close the connection without responding.
+    Disconnect,
+    /// Wait for the specified time before responding.
+    Delay(Duration),
+    /// Respond with bytes that are not a valid message.
+    Garbage,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    settings: SettingsMap,
+    rejected: HashSet<Mac>,
+    delay: Duration,
+    faults: VecDeque<Fault>,
+    requests: Vec<Request>,
+    lenient: bool,
+    /// Sessions that can be resumed, along with the node that owns them.
+    sessions: HashMap<SessionToken, (Mac, Progress)>,
+    last_session: SessionToken,
+}
+
+/// Requests handled in a session, which decide what the node may send next.
+#[derive(Debug, Default)]
+struct Progress {
+    posted: bool,
+    relayed: HashSet<Mac>,
+    replay: Option<(Request, Response)>,
+}
+
+#[derive(Debug, Default)]
+struct Connection {
+    session: Option<(SessionToken, Mac)>,
+    progress: Progress,
+    /// The last response of a resumed session, replayed if the first request was retried.
+    retry: Option<(Request, Response)>,
+}
+
+impl MockServer {
+    /// Start a server on an ephemeral port.
+    ///
+    /// # Errors
+    /// If the listener could not be bound.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let acceptor = {
+            let state = state.clone();
+            let stop = stop.clone();
+
+            thread::spawn(move || accept(&listener, &state, &stop))
+        };
+
+        Ok(Self {
+            addr,
+            state,
+            stop,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Returns the address the server is listening on.
+    #[must_use]
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Set the value returned for a setting. Settings that were not set have their default value.
+    pub fn set_setting(&self, name: SettingName, value: SettingValue) {
+        self.lock().settings.insert(name, value);
+    }
+
+    /// Reject the specified node when it connects, or when a gateway relays its requests.
+    pub fn reject_node(&self, mac: Mac) {
+        self.lock().rejected.insert(mac);
+    }
+
+    /// Wait for the specified time before every response.
+    pub fn set_delay(&self, delay: Duration) {
+        self.lock().delay = delay;
+    }
+
+    /// Check that requests arrive in the order the real server expects, which is the default.
+    /// Otherwise, every request is answered and retried requests are handled again.
+    pub fn set_strict(&self, strict: bool) {
+        self.lock().lenient = !strict;
+    }
+
+    /// Misbehave when handling the next request. Multiple faults are applied to
+    /// consecutive requests, in the order they were injected.
+    pub fn inject(&self, fault: Fault) {
+        self.lock().faults.push_back(fault);
+    }
+
+    /// Returns all requests received so far, including greetings, in the order they arrived.
+    #[must_use]
+    pub fn requests(&self) -> Vec<Request> {
+        self.lock().requests.clone()
+    }
+
+    /// Forget the requests received so far.
+    pub fn clear_requests(&self) {
+        self.lock().requests.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // Wake up the acceptor, so it notices that it should stop.
+        let _ = TcpStream::connect(self.addr);
+
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+    }
+}
+
+impl State {
+    /// Returns the response to a request, or `None` if the connection should be closed.
+    fn respond(&mut self, conn: &mut Connection, request: &Request) -> Option<Response> {
+        match request {
+            Request::Hello { mac } | Request::Resume { mac, .. } if self.rejected.contains(mac) => {
+                Some(Response::Reject)
+            }
+            Request::Hello { .. } => Some(Response::Ok),
+            Request::Resume { mac, session } => Some(self.resume(conn, mac, *session)),
+            Request::Bye => {
+                conn.session = None;
+                None
+            }
+            other => {
+                if let Some((last_request, last_response)) = conn.retry.take() {
+                    if !self.lenient && *other == last_request {
+                        return Some(last_response);
+                    }
+                }
+
+                let Some(response) = self.handle(&mut conn.progress, other) else {
+                    // The real server ends the session of nodes that misbehave.
+                    conn.session = None;
+                    return None;
+                };
+
+                if !self.lenient && is_stateful(other) {
+                    conn.progress.replay = Some((other.clone(), response.clone()));
+                }
+
+                Some(response)
+            }
+        }
+    }
+
+    fn handle(&self, progress: &mut Progress, request: &Request) -> Option<Response> {
+        match request {
+            Request::Relay { child, request } => self.relay(progress, child, request),
+            Request::PostResults { .. } if progress.posted && !self.lenient => None,
+            Request::PostResults { .. } => {
+                progress.posted = true;
+                Some(Response::Ok)
+            }
+            Request::PostStats { .. } if !progress.posted && !self.lenient => None,
+            other => Some(self.serve(other)),
+        }
+    }
+
+    fn serve(&self, request: &Request) -> Response {
+        match request {
+            Request::Ping => Response::Pong,
+            Request::GetSettings(names) => Response::Settings(
+                names
+                    .iter()
+                    .map(|name| {
+                        self.settings
+                            .get(name)
+                            .cloned()
+                            .unwrap_or_else(|| name.default_value())
+                    })
+                    .collect(),
+            ),
+            _ => Response::Ok,
+        }
+    }
+
+    fn relay(&self, progress: &mut Progress, child: &Mac, request: &Request) -> Option<Response> {
+        match request {
+            Request::Ping
+            | Request::Hello { .. }
+            | Request::Resume { .. }
+            | Request::Bye
+            | Request::Relay { .. } => self.lenient.then_some(Response::Reject),
+            _ if self.rejected.contains(child) => Some(Response::Reject),
+            Request::PostResults { .. } => {
+                progress.relayed.insert(child.clone());
+                Some(Response::Ok)
+            }
+            Request::PostStats { .. } if !progress.relayed.contains(child) && !self.lenient => None,
+            other => Some(self.serve(other)),
+        }
+    }
+
+    fn resume(&mut self, conn: &mut Connection, mac: &Mac, token: SessionToken) -> Response {
+        let owned = self
+            .sessions
+            .get(&token)
+            .is_some_and(|(owner, _)| owner == mac);
+
+        if let Some((_, mut progress)) = owned.then(|| self.sessions.remove(&token)).flatten() {
+            conn.retry = progress.replay.take();
+            conn.progress = progress;
+            conn.session = Some((token, mac.clone()));
+
+            return Response::Welcome {
+                session: token,
+                resumed: true,
+            };
+        }
+
+        self.last_session += 1;
+        conn.progress = Progress::default();
+        conn.session = Some((self.last_session, mac.clone()));
+
+        Response::Welcome {
+            session: self.last_session,
+            resumed: false,
+        }
+    }
+}
+
+fn is_stateful(request: &Request) -> bool {
+    match request {
+        Request::PostResults { .. }
+        | Request::PostQueued { .. }
+        | Request::PostStats { .. }
+        | Request::PostLocation { .. }
+        | Request::SendNotification { .. } => true,
+        Request::Relay { request, .. } => is_stateful(request),
+        _ => false,
+    }
+}
+
+fn accept(listener: &TcpListener, state: &Arc<Mutex<State>>, stop: &AtomicBool) {
+    for socket in listener.incoming() {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let Ok(socket) = socket else {
+            continue;
+        };
+
+        let state = state.clone();
+        thread::spawn(move || {
+            let mut conn = Connection::default();
+            let _ = serve(&socket, &state, &mut conn);
+
+            // Sessions can be resumed unless they were ended, like on the real server.
+            if let Some((token, mac)) = conn.session {
+                let mut progress = conn.progress;
+                progress.replay = progress.replay.or(conn.retry);
+
+                lock(&state).sessions.insert(token, (mac, progress));
+            }
+        });
+    }
+}
+
+fn serve(mut socket: &TcpStream, state: &Mutex<State>, conn: &mut Connection) -> io::Result<()> {
+    let mut buf = [0; 256];
+    let mut greeted = false;
+
+    loop {
+        let read = socket.read(&mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
+
+        // Like the real server, give up on clients that send garbage.
+        let Some(request) = Message::from_raw(&buf[..read]).and_then(Message::to_request) else {
+            return Ok(());
+        };
+
+        // The first request must be a greeting, and only the first one.
+        let greeting = matches!(request, Request::Hello { .. } | Request::Resume { .. });
+        if greeting == greeted {
+            return Ok(());
+        }
+        greeted = true;
+
+        let (fault, delay, response) = {
+            let mut state = lock(state);
+            let fault = state.faults.pop_front();
+
+            // Rejected requests are not handled, so they don't change the session.
+            let response = match fault {
+                Some(Fault::Reject) => Some(Response::Reject),
+                Some(Fault::Postpone) => Some(Response::Postponed),
+                _ => state.respond(conn, &request),
+            };
+            state.requests.push(request);
+
+            (fault, state.delay, response)
+        };
+
+        thread::sleep(delay);
+
+        let response = match fault {
+            Some(Fault::Disconnect) => return Ok(()),
+            Some(Fault::Garbage) => {
+                socket.write_all(&GARBAGE)?;
+                continue;
+            }
+            Some(Fault::Delay(delay)) => {
+                thread::sleep(delay);
+                response
+            }
+            Some(Fault::Reject | Fault::Postpone) | None => response,
+        };
+
+        let Some(response) = response else {
+            return socket.shutdown(Shutdown::Both);
+        };
+
+        let rejected = response == Response::Reject;
+        socket.write_all(&Message::Response(response).to_raw())?;
+
+        if greeting && rejected {
+            return socket.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
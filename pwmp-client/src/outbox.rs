@@ -0,0 +1,329 @@
+use crate::error::IoError;
+use alloc::{borrow::ToOwned, boxed::Box, vec::Vec};
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use pwmp_types::Message;
+use pwmp_types::{
+    aliases::{AirPressure, BatteryVoltage, Humidity, Rssi, Temperature, Timestamp},
+    request::Request,
+};
+#[cfg(feature = "std")]
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A measurement that could not be delivered to the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedMeasurement {
+    /// When the measurement was taken, as a UNIX timestamp in seconds.
+    pub measured_at: Timestamp,
+    /// Temperature
+    pub temperature: Temperature,
+    /// Humidity
+    pub humidity: Humidity,
+    /// Air pressure *(if supported by the node)*
+    pub air_pressure: Option<AirPressure>,
+    /// Statistics of the node at the time of the measurement.
+    pub stats: Option<QueuedStats>,
+}
+
+/// Node statistics belonging to a [`QueuedMeasurement`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedStats {
+    /// Node's battery voltage
+    pub battery: BatteryVoltage,
+    /// ESSID of the wireless network
+    pub wifi_ssid: Box<str>,
+    /// RSSI of the connection to the wireless network
+    pub wifi_rssi: Rssi,
+}
+
+/// What to do when a measurement is queued while the outbox is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Drop the oldest queued measurement to make space for the new one.
+    #[default]
+    DropOldest,
+    /// Keep the queued measurements and drop the new one.
+    DropNewest,
+}
+
+/// Persistent storage of an [`Outbox`].
+pub trait OutboxStorage {
+    /// Load all stored measurements, oldest first.
+    fn load(&mut self) -> Result<Vec<QueuedMeasurement>, IoError>;
+
+    /// Replace the stored measurements.
+    fn store(&mut self, entries: &[QueuedMeasurement]) -> Result<(), IoError>;
+}
+
+/// Stores the outbox in a file. Measurements are stored as length-prefixed PWMP messages.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+/// Keeps the outbox in memory only. Queued measurements are lost when the node restarts.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage(Vec<QueuedMeasurement>);
+
+/// Queue of measurements that will be posted once the server is reachable again.
+///
+/// Attach it to a client using [`PwmpClient::attach_outbox()`](crate::PwmpClient::attach_outbox).
+/// If the client cannot connect at all, use [`push()`](Self::push) to queue the measurement manually.
+pub struct Outbox {
+    storage: Box<dyn OutboxStorage + Send>,
+    entries: Vec<QueuedMeasurement>,
+    capacity: usize,
+    policy: DropPolicy,
+}
+
+impl QueuedMeasurement {
+    /// Create a measurement taken right now, according to the system clock.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn now(
+        temperature: Temperature,
+        humidity: Humidity,
+        air_pressure: Option<AirPressure>,
+    ) -> Self {
+        let measured_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+
+        Self {
+            measured_at,
+            temperature,
+            humidity,
+            air_pressure,
+            stats: None,
+        }
+    }
+
+    pub(crate) fn to_request(&self) -> Request {
+        let stats = self.stats.as_ref();
+
+        Request::PostQueued {
+            measured_at: self.measured_at,
+            temperature: self.temperature,
+            humidity: self.humidity,
+            air_pressure: self.air_pressure,
+            battery: stats.map(|stats| stats.battery),
+            wifi_ssid: stats.map(|stats| stats.wifi_ssid.clone()),
+            wifi_rssi: stats.map(|stats| stats.wifi_rssi),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn from_request(request: Request) -> Option<Self> {
+        let Request::PostQueued {
+            measured_at,
+            temperature,
+            humidity,
+            air_pressure,
+            battery,
+            wifi_ssid,
+            wifi_rssi,
+        } = request
+        else {
+            return None;
+        };
+
+        let stats = match (battery, wifi_ssid, wifi_rssi) {
+            (Some(battery), Some(wifi_ssid), Some(wifi_rssi)) => Some(QueuedStats {
+                battery,
+                wifi_ssid,
+                wifi_rssi,
+            }),
+            _ => None,
+        };
+
+        Some(Self {
+            measured_at,
+            temperature,
+            humidity,
+            air_pressure,
+            stats,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl FileStorage {
+    /// Store the outbox in the specified file. It will be created if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl OutboxStorage for FileStorage {
+    fn load(&mut self) -> io::Result<Vec<QueuedMeasurement>> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(why) => return Err(why),
+        };
+
+        let mut entries = Vec::new();
+        let mut rest = contents.as_slice();
+
+        // A truncated entry at the end is ignored, it was not fully written.
+        while let Some((len, tail)) = rest.split_first_chunk::<2>() {
+            let Some((raw, tail)) = tail.split_at_checked(u16::from_le_bytes(*len).into()) else {
+                break;
+            };
+
+            if let Some(entry) = Message::from_raw(raw)
+                .and_then(Message::to_request)
+                .and_then(QueuedMeasurement::from_request)
+            {
+                entries.push(entry);
+            }
+
+            rest = tail;
+        }
+
+        Ok(entries)
+    }
+
+    fn store(&mut self, entries: &[QueuedMeasurement]) -> io::Result<()> {
+        let mut contents = Vec::new();
+
+        for entry in entries {
+            let raw = Message::Request(entry.to_request()).to_raw();
+            let len = u16::try_from(raw.len())
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+
+            contents.extend_from_slice(&len.to_le_bytes());
+            contents.extend_from_slice(&raw);
+        }
+
+        // Write to a temporary file first, so a power loss can't corrupt the outbox.
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, contents)?;
+        fs::rename(temp, &self.path)
+    }
+}
+
+impl OutboxStorage for MemoryStorage {
+    fn load(&mut self) -> Result<Vec<QueuedMeasurement>, IoError> {
+        Ok(self.0.clone())
+    }
+
+    fn store(&mut self, entries: &[QueuedMeasurement]) -> Result<(), IoError> {
+        entries.clone_into(&mut self.0);
+        Ok(())
+    }
+}
+
+impl Outbox {
+    /// Open an outbox holding at most `capacity` measurements. Previously queued measurements
+    /// are loaded from the storage.
+    ///
+    /// # Errors
+    /// If the storage could not be read.
+    pub fn new<S: OutboxStorage + Send + 'static>(
+        mut storage: S,
+        capacity: usize,
+        policy: DropPolicy,
+    ) -> Result<Self, IoError> {
+        let entries = storage.load()?;
+
+        let mut outbox = Self {
+            storage: Box::new(storage),
+            entries,
+            capacity,
+            policy,
+        };
+
+        if outbox.entries.len() > capacity {
+            outbox.enforce_capacity();
+            outbox.save()?;
+        }
+
+        Ok(outbox)
+    }
+
+    /// Queue a measurement. Returns whether it was kept, which is not the case if the outbox
+    /// is full and the [`DropPolicy::DropNewest`] policy is used.
+    ///
+    /// # Errors
+    /// If the storage could not be written.
+    pub fn push(&mut self, measurement: QueuedMeasurement) -> Result<bool, IoError> {
+        if self.entries.len() >= self.capacity && self.policy == DropPolicy::DropNewest {
+            return Ok(false);
+        }
+
+        self.entries.push(measurement);
+        self.enforce_capacity();
+        self.save()?;
+
+        Ok(true)
+    }
+
+    /// Attach statistics to the most recently queued measurement.
+    ///
+    /// # Errors
+    /// If the storage could not be written.
+    pub fn attach_stats(&mut self, stats: QueuedStats) -> Result<(), IoError> {
+        let Some(last) = self.entries.last_mut() else {
+            return Ok(());
+        };
+
+        last.stats = Some(stats);
+        self.save()
+    }
+
+    /// Returns the queued measurements, oldest first.
+    #[must_use]
+    pub fn entries(&self) -> &[QueuedMeasurement] {
+        &self.entries
+    }
+
+    /// Returns the number of queued measurements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether there are no queued measurements.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) -> Result<(), IoError> {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+
+        self.save()
+    }
+
+    fn enforce_capacity(&mut self) {
+        let excess = self.entries.len().saturating_sub(self.capacity);
+
+        match self.policy {
+            DropPolicy::DropOldest => drop(self.entries.drain(..excess)),
+            DropPolicy::DropNewest => self.entries.truncate(self.capacity),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), IoError> {
+        self.storage.store(&self.entries)
+    }
+}
+
+impl Debug for Outbox {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Outbox")
+            .field("entries", &self.entries)
+            .field("capacity", &self.capacity)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
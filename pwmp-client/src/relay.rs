@@ -0,0 +1,162 @@
+#[cfg(feature = "std")]
+use crate::transport::FromStd;
+use crate::{
+    error::Error,
+    settings::{NodeSettings, SettingsMap},
+    transport::Transport,
+    PwmpClient, Result,
+};
+use alloc::boxed::Box;
+use pwmp_types::{
+    aliases::{
+        AirPressure, Altitude, BatteryVoltage, Humidity, Latitude, Longitude, Rssi, Temperature,
+    },
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity, MAX_NOTIFICATION_LEN},
+    request::Request,
+    setting::SettingName,
+};
+#[cfg(feature = "std")]
+use std::net::TcpStream;
+
+/// A node whose requests are relayed through a gateway's [`PwmpClient`].
+///
+/// Created using [`PwmpClient::relay()`]. Unlike regular nodes, a relayed node may post
+/// multiple measurements within one session. Statistics are attached to the latest one.
+pub struct RelayedNode<
+    'c,
+    #[cfg(feature = "std")] T: Transport = FromStd<TcpStream>,
+    #[cfg(not(feature = "std"))] T: Transport,
+> {
+    client: &'c mut PwmpClient<T>,
+    mac: Mac,
+}
+
+impl<'c, T: Transport> RelayedNode<'c, T> {
+    pub(crate) fn new(client: &'c mut PwmpClient<T>, mac: Mac) -> Self {
+        Self { client, mac }
+    }
+
+    /// Returns the MAC address of the relayed node.
+    #[must_use]
+    pub const fn mac(&self) -> &Mac {
+        &self.mac
+    }
+
+    /// Get values of multiple settings of the relayed node.
+    ///
+    /// # Errors
+    /// If the node is not assigned to this gateway, `Err(Error::Rejected)` is returned.
+    pub fn get_settings<const N: usize>(
+        &mut self,
+        settings: [SettingName; N],
+    ) -> Result<[SettingValue; N]> {
+        let req = self.wrap(Request::GetSettings(settings.into()));
+        self.client.settings_request(req)
+    }
+
+    /// Get values of multiple settings of the relayed node, without knowing their amount
+    /// at compile time.
+    ///
+    /// # Errors
+    /// If the node is not assigned to this gateway, `Err(Error::Rejected)` is returned.
+    pub fn get_settings_dyn(&mut self, settings: &[SettingName]) -> Result<SettingsMap> {
+        let req = self.wrap(Request::GetSettings(settings.into()));
+        self.client.settings_map_request(req, settings)
+    }
+
+    /// Get all settings of the relayed node. Missing values are replaced with their defaults.
+    ///
+    /// # Errors
+    /// If the node is not assigned to this gateway, `Err(Error::Rejected)` is returned.
+    pub fn fetch_node_settings(&mut self) -> Result<NodeSettings> {
+        let values = self.get_settings_dyn(&NodeSettings::NAMES)?;
+        Ok(NodeSettings::from_values(&values))
+    }
+
+    /// Post measurements of the relayed node.
+    ///
+    /// # Errors
+    /// If the node is not assigned to this gateway, `Err(Error::Rejected)` is returned.
+    pub fn post_measurements(
+        &mut self,
+        temperature: Temperature,
+        humidity: Humidity,
+        air_pressure: Option<AirPressure>,
+    ) -> Result<()> {
+        let req = self.wrap(Request::PostResults {
+            temperature,
+            humidity,
+            air_pressure,
+        });
+        self.client.request_ok(req)
+    }
+
+    /// Post stats of the relayed node.
+    ///
+    /// # Errors
+    /// If the node is not assigned to this gateway, `Err(Error::Rejected)` is returned.
+    pub fn post_stats(
+        &mut self,
+        battery: BatteryVoltage,
+        wifi_ssid: &str,
+        wifi_rssi: Rssi,
+    ) -> Result<()> {
+        let req = self.wrap(Request::PostStats {
+            battery,
+            wifi_ssid: wifi_ssid.into(),
+            wifi_rssi,
+        });
+        self.client.request_ok(req)
+    }
+
+    /// Send a text notification on behalf of the relayed node.
+    ///
+    /// # Errors
+    /// If the node is not assigned to this gateway, `Err(Error::Rejected)` is returned.
+    /// If the content is longer than [`MAX_NOTIFICATION_LEN`], `Err(Error::NotificationTooLong)`
+    /// is returned.
+    pub fn send_notification(
+        &mut self,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+    ) -> Result<()> {
+        if content.len() > MAX_NOTIFICATION_LEN {
+            return Err(Error::NotificationTooLong);
+        }
+
+        let req = self.wrap(Request::SendNotification {
+            content: content.into(),
+            severity,
+            category,
+        });
+        self.client.request_ok(req)
+    }
+
+    /// Post the location of the relayed node.
+    ///
+    /// # Errors
+    /// If the node is not assigned to this gateway, `Err(Error::Rejected)` is returned.
+    pub fn post_location(
+        &mut self,
+        latitude: Latitude,
+        longitude: Longitude,
+        altitude: Option<Altitude>,
+    ) -> Result<()> {
+        let req = self.wrap(Request::PostLocation {
+            latitude,
+            longitude,
+            altitude,
+        });
+        self.client.request_ok(req)
+    }
+
+    fn wrap(&self, request: Request) -> Request {
+        Request::Relay {
+            child: self.mac.clone(),
+            request: Box::new(request),
+        }
+    }
+}
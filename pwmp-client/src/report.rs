@@ -0,0 +1,77 @@
+use alloc::vec::Vec;
+use core::time::Duration;
+use pwmp_types::request::Request;
+
+/// Timing and traffic of a session, for eg. to find out how long a node had to keep it's
+/// radio on. Returned by [`PwmpClient::report()`](crate::PwmpClient::report) and
+/// [`PwmpClient::close_with_report()`](crate::PwmpClient::close_with_report).
+///
+/// Byte counts only include PWMP messages, not the overhead of TCP or TLS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionReport {
+    /// Number of connections established, including reconnects.
+    pub connections: u32,
+    /// Time spent establishing connections, including failed attempts and TLS handshakes.
+    pub connect_time: Duration,
+    /// Time spent greeting the server with `Hello` or `Resume` requests.
+    pub handshake_time: Duration,
+    /// Every request sent after the greeting, in order. Retried requests appear once per attempt.
+    pub requests: Vec<RequestTiming>,
+    /// Number of bytes sent.
+    pub bytes_sent: u64,
+    /// Number of bytes received.
+    pub bytes_received: u64,
+}
+
+/// Round-trip time of a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTiming {
+    /// Name of the request, for eg. `"PostResults"`.
+    pub request: &'static str,
+    /// Time from sending the request until the response was received, or the attempt failed.
+    pub rtt: Duration,
+    /// Whether the server responded. This is `true` even if the server rejected the request.
+    pub responded: bool,
+}
+
+impl SessionReport {
+    /// Returns the total round-trip time of all requests.
+    #[must_use]
+    pub fn request_time(&self) -> Duration {
+        self.requests.iter().map(|timing| timing.rtt).sum()
+    }
+
+    /// Returns the total time spent connecting, greeting and waiting for responses.
+    #[must_use]
+    pub fn total_time(&self) -> Duration {
+        self.connect_time + self.handshake_time + self.request_time()
+    }
+
+    pub(crate) fn record(&mut self, request: &'static str, rtt: Duration, responded: bool) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(rtt = ?rtt, responded, "request finished");
+
+        self.requests.push(RequestTiming {
+            request,
+            rtt,
+            responded,
+        });
+    }
+}
+
+/// Returns the name of a request, as shown in a [`SessionReport`].
+pub(crate) const fn request_name(req: &Request) -> &'static str {
+    match req {
+        Request::Ping => "Ping",
+        Request::Bye => "Bye",
+        Request::Hello { .. } => "Hello",
+        Request::PostResults { .. } => "PostResults",
+        Request::PostStats { .. } => "PostStats",
+        Request::SendNotification { .. } => "SendNotification",
+        Request::PostLocation { .. } => "PostLocation",
+        Request::GetSettings(..) => "GetSettings",
+        Request::Resume { .. } => "Resume",
+        Request::Relay { .. } => "Relay",
+        Request::PostQueued { .. } => "PostQueued",
+    }
+}
@@ -0,0 +1,105 @@
+use core::time::Duration;
+
+/// Describes how a [`PwmpClient`](crate::PwmpClient) retries connecting to the server.
+///
+/// Between attempts, the client waits for an exponentially growing delay. With jitter enabled,
+/// the delay is randomly shortened by up to a half, so nodes that lost the connection at the
+/// same time don't reconnect all at once.
+///
+/// Requests that change state on the server, like posting measurements, are only retried if
+/// the session was resumed, so the server can tell whether it already processed them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Create a policy that makes at most `max_attempts` attempts in total.
+    #[must_use]
+    pub const fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(8),
+            jitter: true,
+        }
+    }
+
+    /// A policy that never retries.
+    #[must_use]
+    pub const fn never() -> Self {
+        Self::new(1)
+    }
+
+    /// Set the delay before the first retry, and the maximum delay between attempts.
+    #[must_use]
+    pub const fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_delay = initial;
+        self.max_delay = max;
+        self
+    }
+
+    /// Set whether the delay should be randomized.
+    #[must_use]
+    pub const fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns the maximum number of attempts.
+    #[must_use]
+    pub const fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the delay before the specified retry, starting at 1.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn delay(&self, retry: u32) -> Duration {
+        use std::{
+            collections::hash_map::RandomState,
+            hash::{BuildHasher, Hasher},
+        };
+
+        // Only used to spread out reconnects, so the standard library's random hasher keys
+        // are good enough.
+        self.jittered(retry, RandomState::new().build_hasher().finish())
+    }
+
+    /// Same as [`delay()`](Self::delay), using `random` for the jitter.
+    pub(crate) fn jittered(&self, retry: u32, random: u64) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self
+            .initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay);
+
+        if !self.jitter {
+            return delay;
+        }
+
+        let half = delay / 2;
+        half + half.mul_f64(random as f64 / u64::MAX as f64)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Retry once.
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+/// Derive a random number from the time, for targets without a source of randomness. Nodes
+/// that lost the connection at the same time still booted at different times.
+#[cfg(not(feature = "std"))]
+pub(crate) fn random_from(time: Duration) -> u64 {
+    // SplitMix64, so close times result in unrelated numbers.
+    let mut z = (time.as_nanos() as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,309 @@
+#[cfg(feature = "std")]
+use crate::transport::FromStd;
+use crate::{
+    error::Error, outbox::Outbox, report::SessionReport, settings::SettingsMap,
+    transport::Transport, NodeSettings, PwmpClient, Result,
+};
+use alloc::boxed::Box;
+use core::{
+    fmt::{self, Debug, Display},
+    marker::PhantomData,
+};
+#[cfg(feature = "std")]
+use pwmp_types::mac::Mac;
+use pwmp_types::{
+    aliases::{
+        AirPressure, Altitude, BatteryVoltage, Humidity, Latitude, Longitude, Rssi, SessionToken,
+        Temperature,
+    },
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity},
+    setting::SettingName,
+};
+#[cfg(feature = "std")]
+use std::net::{TcpStream, ToSocketAddrs};
+
+/// A session of a node, which can only perform requests in the order required by the protocol:
+/// measurements are posted once, stats only after them, and the session always ends with `Bye`.
+///
+/// Requests that are allowed at any point, like fetching settings or sending notifications,
+/// are available in every state. Tools that need to send requests in any order can use
+/// [`PwmpClient`] directly.
+///
+/// ```no_run
+/// # use pwmp_client::session::Session;
+/// # use pwmp_types::{dec, mac::Mac, Decimal};
+/// let mut session = Session::new("pwmp.example.com:55300", Mac::new(1, 2, 3, 4, 5, 6))?;
+/// let settings = session.fetch_node_settings()?;
+///
+/// let session = session.post_measurements(dec!(21.50), 45, None)?;
+/// let session = session.post_stats(dec!(3.81), "Weather", -67)?;
+/// session.close()?;
+/// # Ok::<(), pwmp_client::error::Error>(())
+/// ```
+///
+/// Stats cannot be posted before the measurements:
+/// ```compile_fail
+/// # use pwmp_client::session::Session;
+/// # use pwmp_types::{dec, mac::Mac, Decimal};
+/// let session = Session::new("pwmp.example.com:55300", Mac::new(1, 2, 3, 4, 5, 6))?;
+/// let session = session.post_stats(dec!(3.81), "Weather", -67)?;
+/// # Ok::<(), pwmp_client::error::Error>(())
+/// ```
+///
+/// And measurements cannot be posted twice:
+/// ```compile_fail
+/// # use pwmp_client::session::Session;
+/// # use pwmp_types::{dec, mac::Mac, Decimal};
+/// let session = Session::new("pwmp.example.com:55300", Mac::new(1, 2, 3, 4, 5, 6))?;
+/// let session = session.post_measurements(dec!(21.50), 45, None)?;
+/// let session = session.post_measurements(dec!(21.50), 45, None)?;
+/// # Ok::<(), pwmp_client::error::Error>(())
+/// ```
+pub struct Session<
+    S: State,
+    #[cfg(feature = "std")] T: Transport = FromStd<TcpStream>,
+    #[cfg(not(feature = "std"))] T: Transport,
+> {
+    client: PwmpClient<T>,
+    state: PhantomData<S>,
+}
+
+/// The node is connected and has not posted measurements yet.
+#[derive(Debug)]
+pub enum Connected {}
+
+/// The node has posted it's measurements.
+#[derive(Debug)]
+pub enum ResultsPosted {}
+
+/// The node has posted it's measurements and stats.
+#[derive(Debug)]
+pub enum StatsPosted {}
+
+/// A state of a [`Session`].
+pub trait State: sealed::Sealed {}
+
+impl State for Connected {}
+impl State for ResultsPosted {}
+impl State for StatsPosted {}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Connected {}
+    impl Sealed for super::ResultsPosted {}
+    impl Sealed for super::StatsPosted {}
+}
+
+/// A request that would have advanced a [`Session`] failed. The session stays in it's
+/// previous state, so for eg. a notification about the failure can still be sent.
+///
+/// Converts into an [`Error`], which ends the session, so it can be propagated using `?`.
+pub struct StepError<
+    S: State,
+    #[cfg(feature = "std")] T: Transport = FromStd<TcpStream>,
+    #[cfg(not(feature = "std"))] T: Transport,
+> {
+    /// The error that occurred.
+    pub error: Error,
+    /// The session in it's previous state.
+    pub session: Box<Session<S, T>>,
+}
+
+#[cfg(feature = "std")]
+impl Session<Connected> {
+    /// Connect to a PWMP server using the default options and start a session.
+    ///
+    /// # Errors
+    /// See [`PwmpClient::new()`].
+    pub fn new<A: ToSocketAddrs>(addr: A, mac: Mac) -> Result<Self> {
+        PwmpClient::new(addr, mac).map(PwmpClient::into_session)
+    }
+}
+
+impl<T: Transport> Session<Connected, T> {
+    /// Post node measurements. See [`PwmpClient::post_measurements()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn post_measurements(
+        mut self,
+        temperature: Temperature,
+        humidity: Humidity,
+        air_pressure: Option<AirPressure>,
+    ) -> core::result::Result<Session<ResultsPosted, T>, StepError<Connected, T>> {
+        match self
+            .client
+            .post_measurements(temperature, humidity, air_pressure)
+        {
+            Ok(()) => Ok(self.advance()),
+            Err(error) => Err(StepError {
+                error,
+                session: Box::new(self),
+            }),
+        }
+    }
+}
+
+impl<T: Transport> Session<ResultsPosted, T> {
+    /// Post node stats. See [`PwmpClient::post_stats()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn post_stats(
+        mut self,
+        battery: BatteryVoltage,
+        wifi_ssid: &str,
+        wifi_rssi: Rssi,
+    ) -> core::result::Result<Session<StatsPosted, T>, StepError<ResultsPosted, T>> {
+        match self.client.post_stats(battery, wifi_ssid, wifi_rssi) {
+            Ok(()) => Ok(self.advance()),
+            Err(error) => Err(StepError {
+                error,
+                session: Box::new(self),
+            }),
+        }
+    }
+}
+
+impl<S: State, T: Transport> Session<S, T> {
+    pub(crate) const fn start(client: PwmpClient<T>) -> Self {
+        Self {
+            client,
+            state: PhantomData,
+        }
+    }
+
+    /// Get values of multiple settings. See [`PwmpClient::get_settings()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn get_settings<const N: usize>(
+        &mut self,
+        settings: [SettingName; N],
+    ) -> Result<[SettingValue; N]> {
+        self.client.get_settings(settings)
+    }
+
+    /// Get values of multiple settings. See [`PwmpClient::get_settings_dyn()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn get_settings_dyn(&mut self, settings: &[SettingName]) -> Result<SettingsMap> {
+        self.client.get_settings_dyn(settings)
+    }
+
+    /// Get all settings of the node. See [`PwmpClient::fetch_node_settings()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn fetch_node_settings(&mut self) -> Result<NodeSettings> {
+        self.client.fetch_node_settings()
+    }
+
+    /// Send a text notification. See [`PwmpClient::send_notification()`].
+    ///
+    /// # Errors
+    /// Generic I/O, or `Err(Error::NotificationTooLong)`.
+    pub fn send_notification(
+        &mut self,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+    ) -> Result<()> {
+        self.client.send_notification(content, severity, category)
+    }
+
+    /// Post the node's location. See [`PwmpClient::post_location()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn post_location(
+        &mut self,
+        latitude: Latitude,
+        longitude: Longitude,
+        altitude: Option<Altitude>,
+    ) -> Result<()> {
+        self.client.post_location(latitude, longitude, altitude)
+    }
+
+    /// Attach an [`Outbox`] and post the measurements queued in it.
+    /// See [`PwmpClient::attach_outbox()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn attach_outbox(&mut self, outbox: Outbox) -> Result<usize> {
+        self.client.attach_outbox(outbox)
+    }
+
+    /// Detach the outbox from the session.
+    pub fn detach_outbox(&mut self) -> Option<Outbox> {
+        self.client.detach_outbox()
+    }
+
+    /// Returns the token of the current session, if the server supports sessions.
+    #[must_use]
+    pub const fn token(&self) -> Option<SessionToken> {
+        self.client.session()
+    }
+
+    /// Returns the timing and traffic of the session so far. See [`PwmpClient::report()`].
+    #[must_use]
+    pub const fn report(&self) -> &SessionReport {
+        self.client.report()
+    }
+
+    /// End the session. See [`PwmpClient::close()`].
+    ///
+    /// # Errors
+    /// Generic I/O.
+    pub fn close(self) -> Result<()> {
+        self.client.close()
+    }
+
+    /// End the session and return the final report. See [`PwmpClient::close_with_report()`].
+    pub fn close_with_report(self) -> (Result<()>, SessionReport) {
+        self.client.close_with_report()
+    }
+
+    fn advance<N: State>(self) -> Session<N, T> {
+        Session::start(self.client)
+    }
+}
+
+impl<S: State, T: Transport> Debug for Session<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("state", &core::any::type_name::<S>())
+            .field("token", &self.token())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: State, T: Transport> Debug for StepError<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StepError")
+            .field("error", &self.error)
+            .field("session", &self.session)
+            .finish()
+    }
+}
+
+impl<S: State, T: Transport> Display for StepError<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl<S: State, T: Transport> core::error::Error for StepError<S, T> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<S: State, T: Transport> From<StepError<S, T>> for Error {
+    fn from(error: StepError<S, T>) -> Self {
+        error.error
+    }
+}
@@ -0,0 +1,71 @@
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+use pwmp_types::{multitype::SettingValue, setting::SettingName};
+
+/// Values of settings, as returned by `get_settings_dyn()`.
+pub type SettingsMap = BTreeMap<SettingName, SettingValue>;
+
+/// Typed settings of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct NodeSettings {
+    /// Whether to ignore the battery level.
+    pub battery_ignore: bool,
+    /// Whether to allow OTA firmware updates.
+    pub ota: bool,
+    /// Time to sleep for after posting measurements.
+    pub sleep_time: Duration,
+    /// Software-based battery overdischarge protection.
+    pub sbop: bool,
+    /// Whether to mute all notifications.
+    pub mute_notifications: bool,
+}
+
+impl NodeSettings {
+    /// All settings needed to fill this struct.
+    pub const NAMES: [SettingName; 5] = [
+        SettingName::BatteryIgnore,
+        SettingName::Ota,
+        SettingName::SleepTime,
+        SettingName::Sbop,
+        SettingName::MuteNotifications,
+    ];
+
+    /// Build the settings from a map of values. Settings that are missing or have
+    /// a value of the wrong type are replaced with their [default value](SettingName::default_value).
+    #[must_use]
+    pub fn from_values(values: &SettingsMap) -> Self {
+        Self {
+            battery_ignore: setting(values, SettingName::BatteryIgnore, SettingValue::as_bool),
+            ota: setting(values, SettingName::Ota, SettingValue::as_bool),
+            sleep_time: Duration::from_secs(
+                setting(values, SettingName::SleepTime, SettingValue::as_number).into(),
+            ),
+            sbop: setting(values, SettingName::Sbop, SettingValue::as_bool),
+            mute_notifications: setting(
+                values,
+                SettingName::MuteNotifications,
+                SettingValue::as_bool,
+            ),
+        }
+    }
+}
+
+impl Default for NodeSettings {
+    fn default() -> Self {
+        Self::from_values(&SettingsMap::new())
+    }
+}
+
+fn setting<T: Default>(
+    values: &SettingsMap,
+    name: SettingName,
+    convert: fn(SettingValue) -> Option<T>,
+) -> T {
+    values
+        .get(&name)
+        .cloned()
+        .and_then(convert)
+        .or_else(|| convert(name.default_value()))
+        .unwrap_or_default()
+}
@@ -0,0 +1,223 @@
+use crate::{
+    error::IoError,
+    transport::{Connector, FromStd, TcpConnector, Transport},
+    Result,
+};
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{self, ring, CryptoProvider},
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    CertificateError, ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore,
+    SignatureScheme, StreamOwned,
+};
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    sync::Arc,
+    time::Duration,
+    vec::Vec,
+};
+
+pub use rustls::pki_types;
+
+/// A TLS connection to a server.
+pub type TlsStream = FromStd<StreamOwned<ClientConnection, TcpStream>>;
+
+/// How a [`PwmpClient`](crate::PwmpClient) verifies the server, and optionally how it
+/// authenticates itself, when connecting over TLS.
+///
+/// ```no_run
+/// # use pwmp_client::{PwmpClient, tls::{TlsOptions, pki_types::{CertificateDer, pem::PemObject}}};
+/// # use pwmp_types::mac::Mac;
+/// let server_cert = CertificateDer::from_pem_file("server.pem").unwrap();
+///
+/// let client = PwmpClient::builder(Mac::new(1, 2, 3, 4, 5, 6))
+///     .build_tls("pwmp.example.com:55300", TlsOptions::pinned(server_cert))?;
+/// # Ok::<(), pwmp_client::error::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TlsOptions {
+    trust: Trust,
+    server_name: ServerName<'static>,
+    identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+}
+
+#[derive(Debug)]
+enum Trust {
+    Pinned(CertificateDer<'static>),
+    Roots(RootCertStore),
+}
+
+/// Only accepts one specific server certificate.
+#[derive(Debug)]
+struct PinnedVerifier {
+    cert: CertificateDer<'static>,
+    provider: Arc<CryptoProvider>,
+}
+
+/// Connects to TCP servers and performs a TLS handshake.
+pub(crate) struct TlsConnector {
+    tcp: TcpConnector,
+    config: Arc<ClientConfig>,
+    server_name: ServerName<'static>,
+}
+
+impl TlsOptions {
+    /// Trust only the specified server certificate, regardless of who issued it and which
+    /// host names it's valid for. This is what nodes should use, since they can't update
+    /// their trusted CAs.
+    #[must_use]
+    pub fn pinned(cert: CertificateDer<'static>) -> Self {
+        Self {
+            trust: Trust::Pinned(cert),
+            server_name: ServerName::IpAddress(Ipv4Addr::UNSPECIFIED.into()),
+            identity: None,
+        }
+    }
+
+    /// Trust server certificates issued by the specified CA, which must be valid for `server_name`.
+    ///
+    /// # Errors
+    /// If the CA certificate is invalid.
+    pub fn ca(ca: CertificateDer<'static>, server_name: ServerName<'static>) -> Result<Self> {
+        let mut roots = RootCertStore::empty();
+        roots.add(ca)?;
+
+        Ok(Self {
+            trust: Trust::Roots(roots),
+            server_name,
+            identity: None,
+        })
+    }
+
+    /// Set the server name sent to the server during the handshake.
+    #[must_use]
+    pub fn server_name(mut self, server_name: ServerName<'static>) -> Self {
+        self.server_name = server_name;
+        self
+    }
+
+    /// Authenticate to the server using a client certificate, which is required if the
+    /// server is configured with a client CA.
+    #[must_use]
+    pub fn identity(
+        mut self,
+        chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Self {
+        self.identity = Some((chain, key));
+        self
+    }
+
+    pub(crate) fn into_connector(self, addrs: Vec<SocketAddr>) -> Result<TlsConnector> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+
+        let builder = match self.trust {
+            Trust::Pinned(cert) => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedVerifier { cert, provider })),
+            Trust::Roots(roots) => builder.with_root_certificates(roots),
+        };
+
+        let config = match self.identity {
+            Some((chain, key)) => builder.with_client_auth_cert(chain, key)?,
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(TlsConnector {
+            tcp: TcpConnector::new(addrs),
+            config: Arc::new(config),
+            server_name: self.server_name,
+        })
+    }
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        if *end_entity != self.cert {
+            return Err(CertificateError::ApplicationVerificationFailure.into());
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+impl Connector<TlsStream> for TlsConnector {
+    fn endpoints(&self) -> usize {
+        self.tcp.endpoints()
+    }
+
+    fn connect(&mut self, endpoint: usize, timeout: Duration) -> io::Result<TlsStream> {
+        let FromStd(mut socket) = self.tcp.connect(endpoint, timeout)?;
+        socket.set_read_timeout(Some(timeout))?;
+        socket.set_write_timeout(Some(timeout))?;
+
+        let mut connection = ClientConnection::new(self.config.clone(), self.server_name.clone())
+            .map_err(io::Error::other)?;
+
+        // Complete the handshake right away, so a server with the wrong certificate is
+        // treated like one that could not be connected to.
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket)?;
+        }
+
+        Ok(FromStd(StreamOwned::new(connection, socket)))
+    }
+}
+
+impl Transport for TlsStream {
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.0.sock.set_read_timeout(Some(timeout))
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.0.sock.set_write_timeout(Some(timeout))
+    }
+
+    fn into_io_error(error: io::Error) -> IoError {
+        error
+    }
+}
@@ -0,0 +1,200 @@
+use crate::error::{io_error, IoError};
+use core::time::Duration;
+use embedded_io::{Error as _, ErrorKind, Read, Write};
+#[cfg(feature = "std")]
+use std::{
+    boxed::Box,
+    io,
+    net::{SocketAddr, TcpStream},
+    vec::Vec,
+};
+
+/// A bidirectional stream of bytes that PWMP messages are exchanged over, for eg. a TCP
+/// connection, a TLS stream or the socket of a microcontroller's networking stack.
+///
+/// It's built on the [`embedded_io`] traits, so it doesn't require the standard library.
+/// Streams of the standard library can be adapted using `FromStd` or `NoTimeouts`, which
+/// require the `std` feature.
+pub trait Transport: Read + Write {
+    /// Limit how long reading may block. Transports that don't support timeouts may ignore this.
+    fn set_read_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error> {
+        let _ = timeout;
+        Ok(())
+    }
+
+    /// Limit how long writing may block. Transports that don't support timeouts may ignore this.
+    fn set_write_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error> {
+        let _ = timeout;
+        Ok(())
+    }
+
+    /// Convert an error of the transport to an [`IoError`]. By default, only it's kind is kept.
+    fn into_io_error(error: Self::Error) -> IoError {
+        io_error(error.kind())
+    }
+}
+
+/// Opens connections to one or more servers, which are called endpoints. The client uses it
+/// to connect initially, and to reconnect after the connection was lost. Endpoints are tried
+/// in order, until one of them accepts the node.
+pub trait Connector<T: Transport> {
+    /// Returns the number of endpoints.
+    fn endpoints(&self) -> usize;
+
+    /// Open a new connection to the specified endpoint, waiting at most for `timeout`.
+    fn connect(&mut self, endpoint: usize, timeout: Duration) -> Result<T, IoError>;
+
+    /// Returns whether another connection can be opened. If not, the client gives up right
+    /// away when the connection is lost, instead of waiting for the retry policy's backoff.
+    fn can_reconnect(&self) -> bool {
+        true
+    }
+}
+
+/// Adapts a [`Read`](io::Read) + [`Write`](io::Write) stream of the standard library to the
+/// [`embedded_io`] traits. It's a [`Transport`] if the stream supports timeouts, like a
+/// [`TcpStream`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct FromStd<T>(pub T);
+
+/// Adapts a [`Read`](io::Read) + [`Write`](io::Write) stream of the standard library that
+/// doesn't support timeouts, for eg. an in-memory pipe in tests, to a [`Transport`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct NoTimeouts<T>(pub T);
+
+/// Connects to TCP servers.
+#[cfg(feature = "std")]
+pub(crate) struct TcpConnector(Box<[SocketAddr]>);
+
+/// A transport that was connected by the caller. It cannot be reconnected.
+pub(crate) struct Established<T>(Option<T>);
+
+#[cfg(feature = "std")]
+impl<T> embedded_io::ErrorType for FromStd<T> {
+    type Error = io::Error;
+}
+
+#[cfg(feature = "std")]
+impl<T: io::Read> Read for FromStd<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: io::Write> Write for FromStd<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Transport for FromStd<TcpStream> {
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.0.set_read_timeout(Some(timeout))
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.0.set_write_timeout(Some(timeout))
+    }
+
+    fn into_io_error(error: io::Error) -> IoError {
+        error
+    }
+}
+
+#[cfg(all(feature = "std", unix))]
+impl Transport for FromStd<std::os::unix::net::UnixStream> {
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.0.set_read_timeout(Some(timeout))
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.0.set_write_timeout(Some(timeout))
+    }
+
+    fn into_io_error(error: io::Error) -> IoError {
+        error
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> embedded_io::ErrorType for NoTimeouts<T> {
+    type Error = io::Error;
+}
+
+#[cfg(feature = "std")]
+impl<T: io::Read> Read for NoTimeouts<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: io::Write> Write for NoTimeouts<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: io::Read + io::Write> Transport for NoTimeouts<T> {
+    fn into_io_error(error: io::Error) -> IoError {
+        error
+    }
+}
+
+#[cfg(feature = "std")]
+impl TcpConnector {
+    pub fn new(addrs: Vec<SocketAddr>) -> Self {
+        Self(addrs.into_boxed_slice())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Connector<FromStd<TcpStream>> for TcpConnector {
+    fn endpoints(&self) -> usize {
+        self.0.len()
+    }
+
+    fn connect(&mut self, endpoint: usize, timeout: Duration) -> io::Result<FromStd<TcpStream>> {
+        let addr = self
+            .0
+            .get(endpoint)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))?;
+
+        TcpStream::connect_timeout(addr, timeout).map(FromStd)
+    }
+}
+
+impl<T> Established<T> {
+    pub const fn new(transport: T) -> Self {
+        Self(Some(transport))
+    }
+}
+
+impl<T: Transport> Connector<T> for Established<T> {
+    fn endpoints(&self) -> usize {
+        1
+    }
+
+    fn connect(&mut self, _endpoint: usize, _timeout: Duration) -> Result<T, IoError> {
+        // The transport doesn't support reconnecting.
+        self.0
+            .take()
+            .ok_or_else(|| io_error(ErrorKind::NotConnected))
+    }
+    fn can_reconnect(&self) -> bool {
+        self.0.is_some()
+    }
+}
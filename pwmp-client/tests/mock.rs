@@ -0,0 +1,426 @@
+use pwmp_client::{
+    error::Error,
+    mock::{Fault, MockServer},
+    outbox::{DropPolicy, MemoryStorage, Outbox, OutboxStorage, QueuedMeasurement},
+    retry::RetryPolicy,
+    session::Session,
+    transport::FromStd,
+    PwmpClient,
+};
+use pwmp_types::{
+    dec,
+    mac::Mac,
+    multitype::SettingValue,
+    request::{Request, NEW_SESSION},
+    setting::SettingName,
+    Decimal,
+};
+use std::{
+    io,
+    net::TcpStream,
+    thread,
+    time::{Duration, Instant},
+};
+
+const MAC: Mac = Mac::new(1, 2, 3, 4, 5, 6);
+
+/// Outbox storage that cannot be written, for eg. because the flash is worn out.
+struct ReadOnlyStorage;
+
+impl OutboxStorage for ReadOnlyStorage {
+    fn load(&mut self) -> io::Result<Vec<QueuedMeasurement>> {
+        Ok(Vec::new())
+    }
+
+    fn store(&mut self, _entries: &[QueuedMeasurement]) -> io::Result<()> {
+        Err(io::ErrorKind::PermissionDenied.into())
+    }
+}
+
+fn quick_retry() -> RetryPolicy {
+    RetryPolicy::new(2).backoff(Duration::from_millis(10), Duration::from_millis(10))
+}
+
+#[test]
+pub fn ping() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+
+    assert!(client.ping());
+    client.close().unwrap();
+
+    assert_eq!(
+        server.requests(),
+        [
+            Request::Resume {
+                mac: MAC,
+                session: NEW_SESSION
+            },
+            Request::Ping,
+            Request::Bye
+        ]
+    );
+}
+
+#[test]
+pub fn get_settings() {
+    let server = MockServer::start().unwrap();
+    server.set_setting(SettingName::SleepTime, SettingValue::Number(300));
+
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+    let values = client
+        .get_settings([SettingName::SleepTime, SettingName::Ota])
+        .unwrap();
+    client.close().unwrap();
+
+    assert_eq!(
+        values,
+        [SettingValue::Number(300), SettingName::Ota.default_value()]
+    );
+}
+
+#[test]
+pub fn post_measurements() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+
+    client
+        .post_measurements(dec!(21.50), 45, Some(1013))
+        .unwrap();
+    client.post_stats(dec!(3.81), "Weather", -67).unwrap();
+    client.close().unwrap();
+
+    assert_eq!(
+        server.requests()[1..3],
+        [
+            Request::PostResults {
+                temperature: dec!(21.50),
+                humidity: 45,
+                air_pressure: Some(1013),
+            },
+            Request::PostStats {
+                battery: dec!(3.81),
+                wifi_ssid: "Weather".into(),
+                wifi_rssi: -67,
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn rejected_node() {
+    let server = MockServer::start().unwrap();
+    server.reject_node(MAC);
+
+    let result = PwmpClient::new(server.addr(), MAC);
+
+    assert!(matches!(result, Err(Error::Rejected)));
+}
+
+#[test]
+pub fn rejected_request() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+
+    server.inject(Fault::Reject);
+    let result = client.post_measurements(dec!(21.50), 45, None);
+
+    assert!(matches!(result, Err(Error::Rejected)));
+}
+
+#[test]
+pub fn malformed_response() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+
+    server.inject(Fault::Garbage);
+    let result = client.get_settings([SettingName::Ota]);
+
+    assert!(matches!(result, Err(Error::MessageParse)));
+}
+
+#[test]
+pub fn read_timeout() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::builder(MAC)
+        .read_timeout(Duration::from_millis(100))
+        .retry(RetryPolicy::never())
+        .build(server.addr())
+        .unwrap();
+
+    server.inject(Fault::Delay(Duration::from_millis(500)));
+
+    assert!(!client.ping());
+}
+
+#[test]
+pub fn resume_after_disconnect() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::builder(MAC)
+        .retry(quick_retry())
+        .build(server.addr())
+        .unwrap();
+    let session = client.session().unwrap();
+
+    server.inject(Fault::Disconnect);
+    client.post_measurements(dec!(21.50), 45, None).unwrap();
+    client.close().unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[2], Request::Resume { mac: MAC, session });
+    assert_eq!(requests[1], requests[3]);
+}
+
+#[test]
+pub fn established_transport_not_retried() {
+    let server = MockServer::start().unwrap();
+    let socket = TcpStream::connect(server.addr()).unwrap();
+    let mut client = PwmpClient::builder(MAC)
+        .retry(RetryPolicy::new(5).backoff(Duration::from_secs(1), Duration::from_secs(1)))
+        .build_with_transport(FromStd(socket))
+        .unwrap();
+
+    // The client can't open a new connection, so it must not wait before giving up.
+    server.inject(Fault::Disconnect);
+    let started = Instant::now();
+    assert!(!client.ping());
+    assert!(started.elapsed() < Duration::from_millis(500));
+}
+
+#[test]
+pub fn out_of_order_request() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::builder(MAC)
+        .retry(RetryPolicy::never())
+        .build(server.addr())
+        .unwrap();
+
+    // Statistics belong to a measurement, so the server gives up on the node.
+    assert!(client.post_stats(dec!(3.81), "Weather", -67).is_err());
+}
+
+#[test]
+pub fn lenient_server() {
+    let server = MockServer::start().unwrap();
+    server.set_strict(false);
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+
+    client.post_stats(dec!(3.81), "Weather", -67).unwrap();
+    client.post_measurements(dec!(21.50), 45, None).unwrap();
+    client.post_measurements(dec!(21.50), 45, None).unwrap();
+    client.close().unwrap();
+}
+
+#[test]
+pub fn retried_request_replayed() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::builder(MAC)
+        .retry(quick_retry())
+        .build(server.addr())
+        .unwrap();
+
+    // The retried measurement is only answered, so statistics can follow it.
+    server.inject(Fault::Disconnect);
+    client.post_measurements(dec!(21.50), 45, None).unwrap();
+    client.post_stats(dec!(3.81), "Weather", -67).unwrap();
+    client.close().unwrap();
+}
+
+#[tokio::test]
+pub async fn async_retry() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::builder(MAC)
+        .retry(RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_millis(10)))
+        .build_async(server.addr())
+        .await
+        .unwrap();
+    let session = client.session().unwrap();
+
+    // The first reconnect fails as well, the second one resumes the session.
+    server.inject(Fault::Disconnect);
+    server.inject(Fault::Disconnect);
+    client
+        .post_measurements(dec!(21.50), 45, None)
+        .await
+        .unwrap();
+    client.close().await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[2], Request::Resume { mac: MAC, session });
+    assert_eq!(requests[3], Request::Resume { mac: MAC, session });
+    assert_eq!(requests[1], requests[4]);
+}
+
+#[test]
+pub fn outbox_flush() {
+    let server = MockServer::start().unwrap();
+    let outbox = Outbox::new(MemoryStorage::default(), 4, DropPolicy::DropOldest).unwrap();
+
+    let mut client = PwmpClient::builder(MAC)
+        .retry(RetryPolicy::never())
+        .deadline(Duration::from_millis(200))
+        .build(server.addr())
+        .unwrap();
+    client.attach_outbox(outbox).unwrap();
+
+    // The measurement is never sent, since the deadline passes first.
+    thread::sleep(Duration::from_millis(250));
+    assert!(matches!(
+        client.post_measurements(dec!(21.50), 45, None),
+        Err(Error::DeadlineExceeded)
+    ));
+    let outbox = client.detach_outbox().unwrap();
+    assert_eq!(outbox.len(), 1);
+    drop(client);
+
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+    assert_eq!(client.attach_outbox(outbox).unwrap(), 1);
+    client.close().unwrap();
+
+    let requests = server.requests();
+    assert!(!requests
+        .iter()
+        .any(|request| matches!(request, Request::PostResults { .. })));
+    assert!(requests
+        .iter()
+        .any(|request| matches!(request, Request::PostQueued { humidity: 45, .. })));
+}
+
+#[test]
+pub fn outbox_failure_keeps_error() {
+    let server = MockServer::start().unwrap();
+    let outbox = Outbox::new(ReadOnlyStorage, 4, DropPolicy::DropOldest).unwrap();
+
+    let mut client = PwmpClient::builder(MAC)
+        .retry(RetryPolicy::never())
+        .deadline(Duration::from_millis(200))
+        .build(server.addr())
+        .unwrap();
+    client.attach_outbox(outbox).unwrap();
+
+    thread::sleep(Duration::from_millis(250));
+    assert!(matches!(
+        client.post_measurements(dec!(21.50), 45, None),
+        Err(Error::DeadlineExceeded)
+    ));
+    assert!(matches!(
+        client.post_stats(dec!(3.81), "Weather", -67),
+        Err(Error::DeadlineExceeded)
+    ));
+}
+
+#[test]
+pub fn outbox_sent_not_queued() {
+    let server = MockServer::start().unwrap();
+    let outbox = Outbox::new(MemoryStorage::default(), 4, DropPolicy::DropOldest).unwrap();
+
+    let mut client = PwmpClient::builder(MAC)
+        .retry(RetryPolicy::never())
+        .build(server.addr())
+        .unwrap();
+    client.attach_outbox(outbox).unwrap();
+
+    // The server received the measurement, so it must not be posted again.
+    server.inject(Fault::Disconnect);
+    assert!(client.post_measurements(dec!(21.50), 45, None).is_err());
+
+    assert!(client.detach_outbox().unwrap().is_empty());
+}
+
+#[test]
+pub fn outbox_keeps_postponed() {
+    let server = MockServer::start().unwrap();
+    let mut outbox = Outbox::new(MemoryStorage::default(), 4, DropPolicy::DropOldest).unwrap();
+    outbox
+        .push(QueuedMeasurement::now(dec!(21.50), 45, None))
+        .unwrap();
+    outbox
+        .push(QueuedMeasurement::now(dec!(22.00), 44, None))
+        .unwrap();
+
+    // The first measurement is from the future, the second one can never be stored.
+    let mut client = PwmpClient::new(server.addr(), MAC).unwrap();
+    server.inject(Fault::Postpone);
+    server.inject(Fault::Reject);
+    assert_eq!(client.attach_outbox(outbox).unwrap(), 0);
+
+    let outbox = client.detach_outbox().unwrap();
+    assert_eq!(outbox.len(), 1);
+    assert_eq!(outbox.entries()[0].humidity, 45);
+}
+
+#[test]
+pub fn session_sequence() {
+    let server = MockServer::start().unwrap();
+    let mut session = Session::new(server.addr(), MAC).unwrap();
+
+    let settings = session.fetch_node_settings().unwrap();
+    let session = session.post_measurements(dec!(21.50), 45, None).unwrap();
+    let session = session.post_stats(dec!(3.81), "Weather", -67).unwrap();
+    session.close().unwrap();
+
+    assert_eq!(settings.sleep_time, Duration::from_secs(60));
+    assert!(matches!(
+        server.requests()[..],
+        [
+            Request::Resume { .. },
+            Request::GetSettings(_),
+            Request::PostResults { .. },
+            Request::PostStats { .. },
+            Request::Bye
+        ]
+    ));
+}
+
+#[test]
+pub fn session_step_error() {
+    let server = MockServer::start().unwrap();
+    let session = Session::new(server.addr(), MAC).unwrap();
+
+    server.inject(Fault::Reject);
+    let failed = session
+        .post_measurements(dec!(21.50), 45, None)
+        .unwrap_err();
+    assert!(matches!(failed.error, Error::Rejected));
+
+    let session = failed
+        .session
+        .post_measurements(dec!(21.50), 45, None)
+        .unwrap();
+    session.close().unwrap();
+}
+
+#[test]
+pub fn session_report() {
+    let server = MockServer::start().unwrap();
+    let mut client = PwmpClient::builder(MAC)
+        .retry(quick_retry())
+        .build(server.addr())
+        .unwrap();
+
+    server.inject(Fault::Delay(Duration::from_millis(50)));
+    assert!(client.ping());
+    server.inject(Fault::Disconnect);
+    client.post_measurements(dec!(21.50), 45, None).unwrap();
+
+    let (result, report) = client.close_with_report();
+    result.unwrap();
+
+    assert_eq!(report.connections, 2);
+    assert_eq!(
+        report
+            .requests
+            .iter()
+            .map(|timing| (timing.request, timing.responded))
+            .collect::<Vec<_>>(),
+        [
+            ("Ping", true),
+            ("PostResults", false),
+            ("PostResults", true)
+        ]
+    );
+    assert!(report.requests[0].rtt >= Duration::from_millis(50));
+    assert!(report.total_time() >= report.request_time());
+    assert!(report.bytes_sent > 0);
+    assert!(report.bytes_received > 0);
+}
@@ -0,0 +1,196 @@
+/* Generated by cbindgen from pwmp-ffi. Do not edit. */
+
+#ifndef PWMP_H
+#define PWMP_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Category of a notification.
+ */
+enum PwmpCategory
+#ifdef __cplusplus
+  : uint8_t
+#endif // __cplusplus
+ {
+  PWMP_CATEGORY_GENERAL = 0,
+  PWMP_CATEGORY_BATTERY = 1,
+  PWMP_CATEGORY_SENSOR = 2,
+  PWMP_CATEGORY_CONNECTIVITY = 3,
+  PWMP_CATEGORY_FIRMWARE = 4,
+};
+#ifndef __cplusplus
+typedef uint8_t PwmpCategory;
+#endif // __cplusplus
+
+/**
+ * Result of a call.
+ */
+typedef enum PwmpError {
+  /**
+   * The call succeeded.
+   */
+  PWMP_ERROR_OK = 0,
+  /**
+   * A pointer was null, a string was not valid UTF-8 or a value was out of range.
+   */
+  PWMP_ERROR_INVALID_ARGUMENT,
+  /**
+   * The address could not be resolved.
+   */
+  PWMP_ERROR_NO_ADDRESS,
+  /**
+   * A network error occurred, for eg. the server could not be reached.
+   */
+  PWMP_ERROR_IO,
+  /**
+   * The server did not respond in time, or the session deadline has passed.
+   */
+  PWMP_ERROR_TIMEOUT,
+  /**
+   * The server rejected the node or the request.
+   */
+  PWMP_ERROR_REJECTED,
+  /**
+   * The server sent an invalid or unexpected response.
+   */
+  PWMP_ERROR_PROTOCOL,
+  /**
+   * The notification is longer than 64 bytes.
+   */
+  PWMP_ERROR_NOTIFICATION_TOO_LONG,
+  /**
+   * An internal error occurred. This is a bug.
+   */
+  PWMP_ERROR_INTERNAL,
+} PwmpError;
+
+/**
+ * Severity of a notification.
+ */
+enum PwmpSeverity
+#ifdef __cplusplus
+  : uint8_t
+#endif // __cplusplus
+ {
+  PWMP_SEVERITY_INFO = 0,
+  PWMP_SEVERITY_WARNING = 1,
+  PWMP_SEVERITY_CRITICAL = 2,
+};
+#ifndef __cplusplus
+typedef uint8_t PwmpSeverity;
+#endif // __cplusplus
+
+/**
+ * A client connected to a PWMP server. Created by [`pwmp_connect()`] and freed by
+ * [`pwmp_close()`].
+ */
+typedef struct PwmpClient PwmpClient;
+
+/**
+ * Settings of a node.
+ */
+typedef struct PwmpSettings {
+  /**
+   * Whether to ignore the battery level.
+   */
+  bool battery_ignore;
+  /**
+   * Whether to allow OTA firmware updates.
+   */
+  bool ota;
+  /**
+   * Time to sleep for after posting measurements, in seconds.
+   */
+  uint32_t sleep_time;
+  /**
+   * Software-based battery overdischarge protection.
+   */
+  bool sbop;
+  /**
+   * Whether to mute all notifications.
+   */
+  bool mute_notifications;
+} PwmpSettings;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Connect to a PWMP server at `addr` (for eg. `"192.168.1.10:55300"`) as the node with
+ * the 6 byte MAC address `mac`. On success, the client is stored in `out`.
+ *
+ * # Safety
+ * `addr` must be a null-terminated string, `mac` must point to 6 bytes and `out` must be
+ * valid for writes.
+ */
+enum PwmpError pwmp_connect(const char *addr, const uint8_t *mac, struct PwmpClient **out);
+
+/**
+ * Get the settings of the node. Settings without a value are set to their defaults.
+ *
+ * # Safety
+ * `client` must be a client returned by [`pwmp_connect()`] and `out` must be valid for writes.
+ */
+enum PwmpError pwmp_get_settings(struct PwmpClient *client, struct PwmpSettings *out);
+
+/**
+ * Post measurements. An `air_pressure` of `0` means the node has no pressure sensor.
+ *
+ * # Safety
+ * `client` must be a client returned by [`pwmp_connect()`].
+ */
+enum PwmpError pwmp_post_measurements(struct PwmpClient *client,
+                                      float temperature,
+                                      uint8_t humidity,
+                                      uint16_t air_pressure);
+
+/**
+ * Post node stats. Must be called after [`pwmp_post_measurements()`].
+ *
+ * # Safety
+ * `client` must be a client returned by [`pwmp_connect()`] and `wifi_ssid` must be
+ * a null-terminated string.
+ */
+enum PwmpError pwmp_post_stats(struct PwmpClient *client,
+                               float battery,
+                               const char *wifi_ssid,
+                               int8_t wifi_rssi);
+
+/**
+ * Send a text notification. `severity` is one of the `PWMP_SEVERITY_*` constants and
+ * `category` one of the `PWMP_CATEGORY_*` constants.
+ *
+ * # Safety
+ * `client` must be a client returned by [`pwmp_connect()`] and `content` must be
+ * a null-terminated string.
+ */
+enum PwmpError pwmp_send_notification(struct PwmpClient *client,
+                                      const char *content,
+                                      uint8_t severity,
+                                      uint8_t category);
+
+/**
+ * End the session and free the client. The client is freed even if an error is returned,
+ * so this must be called exactly once for every connected client.
+ *
+ * # Safety
+ * `client` must be a client returned by [`pwmp_connect()`], which must not be used afterwards.
+ */
+enum PwmpError pwmp_close(struct PwmpClient *client);
+
+/**
+ * Returns a static, null-terminated description of an error code.
+ */
+const char *pwmp_error_message(enum PwmpError error);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PWMP_H */
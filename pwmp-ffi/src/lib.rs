@@ -0,0 +1,327 @@
+//! C bindings for [`pwmp_client`], for node firmware that is not written in Rust.
+//!
+//! Every function returns a [`PwmpError`] instead of panicking. The generated header is
+//! located at `include/pwmp.h`.
+#![allow(clippy::missing_panics_doc)]
+use pwmp_client::{
+    error::Error,
+    pwmp_types::{
+        mac::Mac,
+        notification::{NotificationCategory, NotificationSeverity},
+        Decimal,
+    },
+    settings::NodeSettings,
+};
+use std::{
+    ffi::{c_char, CStr},
+    io,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+};
+
+/// A client connected to a PWMP server. Created by [`pwmp_connect()`] and freed by
+/// [`pwmp_close()`].
+pub struct PwmpClient(pwmp_client::PwmpClient);
+
+/// Result of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PwmpError {
+    /// The call succeeded.
+    Ok = 0,
+    /// A pointer was null, a string was not valid UTF-8 or a value was out of range.
+    InvalidArgument,
+    /// The address could not be resolved.
+    NoAddress,
+    /// A network error occurred, for eg. the server could not be reached.
+    Io,
+    /// The server did not respond in time, or the session deadline has passed.
+    Timeout,
+    /// The server rejected the node or the request.
+    Rejected,
+    /// The server sent an invalid or unexpected response.
+    Protocol,
+    /// The notification is longer than 64 bytes.
+    NotificationTooLong,
+    /// An internal error occurred. This is a bug.
+    Internal,
+}
+
+/// Severity of a notification.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PwmpSeverity {
+    Info = 0,
+    Warning = 1,
+    Critical = 2,
+}
+
+/// Category of a notification.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PwmpCategory {
+    General = 0,
+    Battery = 1,
+    Sensor = 2,
+    Connectivity = 3,
+    Firmware = 4,
+}
+
+/// Settings of a node.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct PwmpSettings {
+    /// Whether to ignore the battery level.
+    pub battery_ignore: bool,
+    /// Whether to allow OTA firmware updates.
+    pub ota: bool,
+    /// Time to sleep for after posting measurements, in seconds.
+    pub sleep_time: u32,
+    /// Software-based battery overdischarge protection.
+    pub sbop: bool,
+    /// Whether to mute all notifications.
+    pub mute_notifications: bool,
+}
+
+/// Connect to a PWMP server at `addr` (for eg. `"192.168.1.10:55300"`) as the node with
+/// the 6 byte MAC address `mac`. On success, the client is stored in `out`.
+///
+/// # Safety
+/// `addr` must be a null-terminated string, `mac` must point to 6 bytes and `out` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn pwmp_connect(
+    addr: *const c_char,
+    mac: *const u8,
+    out: *mut *mut PwmpClient,
+) -> PwmpError {
+    guard(|| {
+        if mac.is_null() || out.is_null() {
+            return Err(PwmpError::InvalidArgument);
+        }
+
+        let addr = string(addr)?;
+        let mac = ptr::read(mac.cast::<[u8; 6]>());
+        let mac = Mac::new(mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]);
+
+        let client = pwmp_client::PwmpClient::new(addr, mac)?;
+        out.write(Box::into_raw(Box::new(PwmpClient(client))));
+
+        Ok(())
+    })
+}
+
+/// Get the settings of the node. Settings without a value are set to their defaults.
+///
+/// # Safety
+/// `client` must be a client returned by [`pwmp_connect()`] and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn pwmp_get_settings(
+    client: *mut PwmpClient,
+    out: *mut PwmpSettings,
+) -> PwmpError {
+    guard(|| {
+        let client = client_mut(client)?;
+        if out.is_null() {
+            return Err(PwmpError::InvalidArgument);
+        }
+
+        let settings = client.fetch_node_settings()?;
+        out.write(PwmpSettings::from(settings));
+
+        Ok(())
+    })
+}
+
+/// Post measurements. An `air_pressure` of `0` means the node has no pressure sensor.
+///
+/// # Safety
+/// `client` must be a client returned by [`pwmp_connect()`].
+#[no_mangle]
+pub unsafe extern "C" fn pwmp_post_measurements(
+    client: *mut PwmpClient,
+    temperature: f32,
+    humidity: u8,
+    air_pressure: u16,
+) -> PwmpError {
+    guard(|| {
+        let client = client_mut(client)?;
+        let air_pressure = (air_pressure != 0).then_some(air_pressure);
+
+        client.post_measurements(decimal(temperature)?, humidity, air_pressure)?;
+        Ok(())
+    })
+}
+
+/// Post node stats. Must be called after [`pwmp_post_measurements()`].
+///
+/// # Safety
+/// `client` must be a client returned by [`pwmp_connect()`] and `wifi_ssid` must be
+/// a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pwmp_post_stats(
+    client: *mut PwmpClient,
+    battery: f32,
+    wifi_ssid: *const c_char,
+    wifi_rssi: i8,
+) -> PwmpError {
+    guard(|| {
+        let client = client_mut(client)?;
+        let wifi_ssid = string(wifi_ssid)?;
+
+        client.post_stats(decimal(battery)?, wifi_ssid, wifi_rssi)?;
+        Ok(())
+    })
+}
+
+/// Send a text notification. `severity` is one of the `PWMP_SEVERITY_*` constants and
+/// `category` one of the `PWMP_CATEGORY_*` constants.
+///
+/// # Safety
+/// `client` must be a client returned by [`pwmp_connect()`] and `content` must be
+/// a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pwmp_send_notification(
+    client: *mut PwmpClient,
+    content: *const c_char,
+    severity: u8,
+    category: u8,
+) -> PwmpError {
+    guard(|| {
+        let client = client_mut(client)?;
+        let content = string(content)?;
+
+        client.send_notification(content, severity_from(severity)?, category_from(category)?)?;
+        Ok(())
+    })
+}
+
+/// End the session and free the client. The client is freed even if an error is returned,
+/// so this must be called exactly once for every connected client.
+///
+/// # Safety
+/// `client` must be a client returned by [`pwmp_connect()`], which must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pwmp_close(client: *mut PwmpClient) -> PwmpError {
+    guard(|| {
+        if client.is_null() {
+            return Err(PwmpError::InvalidArgument);
+        }
+
+        Box::from_raw(client).0.close()?;
+        Ok(())
+    })
+}
+
+/// Returns a static, null-terminated description of an error code.
+#[no_mangle]
+pub extern "C" fn pwmp_error_message(error: PwmpError) -> *const c_char {
+    let message = match error {
+        PwmpError::Ok => c"success",
+        PwmpError::InvalidArgument => c"invalid argument",
+        PwmpError::NoAddress => c"address could not be resolved",
+        PwmpError::Io => c"network error",
+        PwmpError::Timeout => c"timed out",
+        PwmpError::Rejected => c"server rejected",
+        PwmpError::Protocol => c"invalid response",
+        PwmpError::NotificationTooLong => c"notification too long",
+        PwmpError::Internal => c"internal error",
+    };
+
+    message.as_ptr()
+}
+
+impl From<Error> for PwmpError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(why) if is_timeout(&why) => Self::Timeout,
+            Error::Io(_) => Self::Io,
+            Error::Rejected | Error::Postponed => Self::Rejected,
+            Error::NotResponse
+            | Error::MessageParse
+            | Error::UnexpectedVariant
+            | Error::MalformedResponse => Self::Protocol,
+            Error::NoAddress => Self::NoAddress,
+            Error::DeadlineExceeded => Self::Timeout,
+            Error::NotificationTooLong => Self::NotificationTooLong,
+            // Variants behind features of `pwmp-client` this crate does not use, like TLS.
+            #[allow(unreachable_patterns)]
+            _ => Self::Internal,
+        }
+    }
+}
+
+impl From<NodeSettings> for PwmpSettings {
+    fn from(settings: NodeSettings) -> Self {
+        Self {
+            battery_ignore: settings.battery_ignore,
+            ota: settings.ota,
+            sleep_time: u32::try_from(settings.sleep_time.as_secs()).unwrap_or(u32::MAX),
+            sbop: settings.sbop,
+            mute_notifications: settings.mute_notifications,
+        }
+    }
+}
+
+/// Run the body of a function, turning panics into [`PwmpError::Internal`].
+fn guard<F: FnOnce() -> Result<(), PwmpError>>(body: F) -> PwmpError {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => PwmpError::Ok,
+        Ok(Err(error)) => error,
+        Err(_) => PwmpError::Internal,
+    }
+}
+
+unsafe fn client_mut<'a>(
+    client: *mut PwmpClient,
+) -> Result<&'a mut pwmp_client::PwmpClient, PwmpError> {
+    client
+        .as_mut()
+        .map(|client| &mut client.0)
+        .ok_or(PwmpError::InvalidArgument)
+}
+
+unsafe fn string<'a>(raw: *const c_char) -> Result<&'a str, PwmpError> {
+    if raw.is_null() {
+        return Err(PwmpError::InvalidArgument);
+    }
+
+    CStr::from_ptr(raw)
+        .to_str()
+        .map_err(|_| PwmpError::InvalidArgument)
+}
+
+/// Convert a float to a decimal with two decimal places, like the ones stored by the server.
+fn decimal(value: f32) -> Result<Decimal, PwmpError> {
+    Decimal::try_from(value)
+        .map(|value| value.round_dp(2))
+        .map_err(|_| PwmpError::InvalidArgument)
+}
+
+const fn severity_from(raw: u8) -> Result<NotificationSeverity, PwmpError> {
+    Ok(match raw {
+        0 => NotificationSeverity::Info,
+        1 => NotificationSeverity::Warning,
+        2 => NotificationSeverity::Critical,
+        _ => return Err(PwmpError::InvalidArgument),
+    })
+}
+
+const fn category_from(raw: u8) -> Result<NotificationCategory, PwmpError> {
+    Ok(match raw {
+        0 => NotificationCategory::General,
+        1 => NotificationCategory::Battery,
+        2 => NotificationCategory::Sensor,
+        3 => NotificationCategory::Connectivity,
+        4 => NotificationCategory::Firmware,
+        _ => return Err(PwmpError::InvalidArgument),
+    })
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
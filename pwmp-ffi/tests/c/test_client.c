@@ -0,0 +1,65 @@
+/*
+ * Runs a full node session against a PWMP server using the C bindings.
+ *
+ * Build and run it from the repository root, after building the library:
+ *   cargo build -p pwmp-ffi
+ *   cc pwmp-ffi/tests/c/test_client.c -Ipwmp-ffi/include -Ltarget/debug -lpwmp -o test_client
+ *   LD_LIBRARY_PATH=target/debug ./test_client 127.0.0.1:55300
+ *
+ * The node 01:02:03:04:05:06 must be registered on the server.
+ */
+#include <stdio.h>
+
+#include "pwmp.h"
+
+#define CHECK(call)                                                                 \
+    do {                                                                            \
+        PwmpError error = (call);                                                   \
+        if (error != PWMP_ERROR_OK) {                                               \
+            fprintf(stderr, "%s failed: %s\n", #call, pwmp_error_message(error));   \
+            failed = 1;                                                             \
+            goto close;                                                             \
+        }                                                                           \
+    } while (0)
+
+int main(int argc, char **argv) {
+    const char *addr = argc > 1 ? argv[1] : "127.0.0.1:55300";
+    const uint8_t mac[6] = {0x01, 0x02, 0x03, 0x04, 0x05, 0x06};
+    PwmpClient *client = NULL;
+    PwmpSettings settings;
+    int failed = 0;
+
+    PwmpError error = pwmp_connect(addr, mac, &client);
+    if (error != PWMP_ERROR_OK) {
+        fprintf(stderr, "Failed to connect to %s: %s\n", addr, pwmp_error_message(error));
+        return 1;
+    }
+
+    CHECK(pwmp_get_settings(client, &settings));
+    printf("Sleep time: %u seconds\n", settings.sleep_time);
+
+    CHECK(pwmp_post_measurements(client, 21.5f, 45, 1013));
+    CHECK(pwmp_post_stats(client, 3.81f, "Weather", -67));
+    CHECK(pwmp_send_notification(client, "Hello from C", PWMP_SEVERITY_INFO,
+                                 PWMP_CATEGORY_GENERAL));
+
+    /* Invalid arguments are reported instead of crashing. */
+    if (pwmp_send_notification(client, "Hello from C", 42, PWMP_CATEGORY_GENERAL) !=
+        PWMP_ERROR_INVALID_ARGUMENT) {
+        fprintf(stderr, "Invalid severity was accepted\n");
+        failed = 1;
+    }
+
+close:
+    error = pwmp_close(client);
+    if (error != PWMP_ERROR_OK) {
+        fprintf(stderr, "Failed to close the session: %s\n", pwmp_error_message(error));
+        failed = 1;
+    }
+
+    if (!failed) {
+        printf("Session completed\n");
+    }
+
+    return failed;
+}
@@ -0,0 +1,104 @@
+use pwmp::{
+    pwmp_close, pwmp_connect, pwmp_get_settings, pwmp_post_measurements, pwmp_post_stats,
+    pwmp_send_notification, PwmpCategory, PwmpClient, PwmpError, PwmpSettings, PwmpSeverity,
+};
+use pwmp_client::{
+    mock::{Fault, MockServer},
+    pwmp_types::{dec, request::Request, Decimal},
+};
+use std::{ffi::CString, ptr};
+
+const MAC: [u8; 6] = [1, 2, 3, 4, 5, 6];
+
+fn connect(server: &MockServer) -> *mut PwmpClient {
+    let addr = CString::new(server.addr().to_string()).unwrap();
+    let mut client = ptr::null_mut();
+
+    let error = unsafe { pwmp_connect(addr.as_ptr(), MAC.as_ptr(), &mut client) };
+    assert_eq!(error, PwmpError::Ok);
+
+    client
+}
+
+#[test]
+pub fn session() {
+    let server = MockServer::start().unwrap();
+    let client = connect(&server);
+    let mut settings = PwmpSettings {
+        battery_ignore: true,
+        ota: true,
+        sleep_time: 0,
+        sbop: false,
+        mute_notifications: true,
+    };
+
+    unsafe {
+        assert_eq!(pwmp_get_settings(client, &mut settings), PwmpError::Ok);
+        assert_eq!(pwmp_post_measurements(client, 21.5, 45, 0), PwmpError::Ok);
+        assert_eq!(
+            pwmp_post_stats(client, 3.81, c"Weather".as_ptr(), -67),
+            PwmpError::Ok
+        );
+        assert_eq!(
+            pwmp_send_notification(
+                client,
+                c"Hello".as_ptr(),
+                PwmpSeverity::Warning as u8,
+                PwmpCategory::Sensor as u8
+            ),
+            PwmpError::Ok
+        );
+        assert_eq!(pwmp_close(client), PwmpError::Ok);
+    }
+
+    assert_eq!(settings.sleep_time, 60);
+    assert_eq!(
+        server.requests()[2..4],
+        [
+            Request::PostResults {
+                temperature: dec!(21.50),
+                humidity: 45,
+                air_pressure: None,
+            },
+            Request::PostStats {
+                battery: dec!(3.81),
+                wifi_ssid: "Weather".into(),
+                wifi_rssi: -67,
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn errors() {
+    let server = MockServer::start().unwrap();
+    let client = connect(&server);
+    let long = CString::new("x".repeat(65)).unwrap();
+
+    unsafe {
+        assert_eq!(
+            pwmp_connect(ptr::null(), MAC.as_ptr(), &mut ptr::null_mut()),
+            PwmpError::InvalidArgument
+        );
+        assert_eq!(
+            pwmp_post_measurements(client, f32::NAN, 45, 0),
+            PwmpError::InvalidArgument
+        );
+        assert_eq!(
+            pwmp_send_notification(client, c"Hello".as_ptr(), 3, 0),
+            PwmpError::InvalidArgument
+        );
+        assert_eq!(
+            pwmp_send_notification(client, long.as_ptr(), 0, 0),
+            PwmpError::NotificationTooLong
+        );
+
+        server.inject(Fault::Reject);
+        assert_eq!(
+            pwmp_post_measurements(client, 21.5, 45, 0),
+            PwmpError::Rejected
+        );
+
+        assert_eq!(pwmp_close(client), PwmpError::Ok);
+    }
+}
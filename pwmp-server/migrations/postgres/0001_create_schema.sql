@@ -1,4 +1,3 @@
-/* CREATE DATABASE "pixelweather"; */
 CREATE TABLE devices (
     id SMALLSERIAL PRIMARY KEY,
     mac_address VARCHAR(17) UNIQUE NOT NULL CHECK (mac_address ~ E'^([0-9A-F]{2}:){5}[0-9A-F]{2}$'),
@@ -0,0 +1,32 @@
+/* Nodes can now have multiple notifications with a severity, category, expiry and read time.
+Existing notifications are kept as general information that doesn't expire. */
+ALTER TABLE notifications DROP CONSTRAINT notifications_node_key;
+ALTER TABLE notifications DROP CONSTRAINT notifications_when_key;
+ALTER TABLE notifications ALTER COLUMN id TYPE INT4;
+ALTER SEQUENCE notifications_id_seq AS INT4;
+ALTER TABLE notifications
+ADD COLUMN last_seen TIMESTAMP,
+    ADD COLUMN expires TIMESTAMP DEFAULT NULL,
+    ADD COLUMN severity VARCHAR(8) NOT NULL DEFAULT 'info' CHECK (
+        severity IN ('info', 'warning', 'critical')
+    ),
+    ADD COLUMN category VARCHAR(12) NOT NULL DEFAULT 'general' CHECK (
+        category IN (
+            'general',
+            'battery',
+            'sensor',
+            'connectivity',
+            'firmware'
+        )
+    ),
+    ADD COLUMN occurrences INT4 NOT NULL DEFAULT 1 CHECK (occurrences > 0),
+    ADD COLUMN read_at TIMESTAMP DEFAULT NULL;
+UPDATE notifications
+SET last_seen = "when";
+ALTER TABLE notifications
+ALTER COLUMN last_seen
+SET NOT NULL,
+    ALTER COLUMN last_seen
+SET DEFAULT NOW();
+CREATE INDEX notifications_unread ON notifications(node)
+WHERE NOT read;
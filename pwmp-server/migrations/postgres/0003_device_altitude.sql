@@ -0,0 +1,3 @@
+/* Altitude reported by nodes along with their location. */
+ALTER TABLE devices
+ADD COLUMN altitude INT2 DEFAULT NULL;
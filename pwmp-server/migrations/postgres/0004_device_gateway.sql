@@ -0,0 +1,3 @@
+/* Gateways that relay requests of nodes which cannot reach the server directly. */
+ALTER TABLE devices
+ADD COLUMN gateway INT2 DEFAULT NULL REFERENCES devices(id) CHECK (gateway <> id);
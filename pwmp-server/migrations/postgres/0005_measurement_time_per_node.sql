@@ -0,0 +1,4 @@
+/* Measurement times only need to be unique per node, so different nodes can post at the same time. */
+ALTER TABLE measurements DROP CONSTRAINT measurements_when_key;
+ALTER TABLE measurements
+ADD CONSTRAINT measurements_node_when_key UNIQUE (node, "when");
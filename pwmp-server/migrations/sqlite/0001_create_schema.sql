@@ -0,0 +1,80 @@
+/* SQLite version of the PostgreSQL schema in migrations/postgres. Timestamps are stored as UTC text, so they sort correctly. */
+CREATE TABLE devices (
+    id INTEGER PRIMARY KEY,
+    mac_address VARCHAR(17) UNIQUE NOT NULL CHECK (
+        mac_address GLOB '[0-9A-F][0-9A-F]:[0-9A-F][0-9A-F]:[0-9A-F][0-9A-F]:[0-9A-F][0-9A-F]:[0-9A-F][0-9A-F]:[0-9A-F][0-9A-F]'
+    ),
+    latitude REAL DEFAULT NULL CHECK (latitude BETWEEN -90 AND 90),
+    longitude REAL DEFAULT NULL CHECK (longitude BETWEEN -180 AND 180),
+    altitude INT2 DEFAULT NULL,
+    gateway INT2 DEFAULT NULL REFERENCES devices(id) CHECK (gateway <> id),
+    note VARCHAR(16) DEFAULT NULL CHECK (LENGTH(note) <= 16),
+    CHECK ((latitude IS NULL) = (longitude IS NULL))
+);
+CREATE TABLE measurements (
+    id INTEGER PRIMARY KEY,
+    node INT2 NOT NULL REFERENCES devices(id),
+    "when" TIMESTAMP NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%f', 'now')),
+    temperature DECIMAL(4, 2) NOT NULL,
+    humidity SMALLINT NOT NULL CHECK (
+        humidity >= 0
+        AND humidity <= 100
+    ),
+    air_pressure SMALLINT DEFAULT NULL,
+    UNIQUE (node, "when")
+);
+CREATE TABLE statistics (
+    id INTEGER PRIMARY KEY,
+    measurement INT2 NOT NULL REFERENCES measurements(id),
+    battery DECIMAL(3, 2) NOT NULL CHECK (battery > 0),
+    wifi_ssid VARCHAR(32) NOT NULL CHECK (LENGTH(wifi_ssid) <= 32),
+    wifi_rssi INT2 NOT NULL
+);
+CREATE TABLE settings (
+    id INTEGER PRIMARY KEY,
+    node INT2 UNIQUE NOT NULL REFERENCES devices(id),
+    battery_ignore BOOLEAN NOT NULL DEFAULT FALSE,
+    ota BOOLEAN NOT NULL DEFAULT FALSE,
+    sleep_time INT2 NOT NULL DEFAULT 60 CHECK (sleep_time > 0),
+    sbop BOOLEAN NOT NULL DEFAULT TRUE,
+    mute_notifications BOOLEAN NOT NULL DEFAULT FALSE,
+    device_specific JSON NOT NULL DEFAULT '{}' CHECK (JSON_VALID(device_specific))
+);
+CREATE TABLE notifications (
+    id INTEGER PRIMARY KEY,
+    node INT2 NOT NULL REFERENCES devices(id),
+    "when" TIMESTAMP NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%f', 'now')),
+    last_seen TIMESTAMP NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%f', 'now')),
+    expires TIMESTAMP DEFAULT NULL,
+    severity VARCHAR(8) NOT NULL DEFAULT 'info' CHECK (
+        severity IN ('info', 'warning', 'critical')
+    ),
+    category VARCHAR(12) NOT NULL DEFAULT 'general' CHECK (
+        category IN (
+            'general',
+            'battery',
+            'sensor',
+            'connectivity',
+            'firmware'
+        )
+    ),
+    content VARCHAR(64) NOT NULL CHECK (LENGTH(content) <= 64),
+    occurrences INT4 NOT NULL DEFAULT 1 CHECK (occurrences > 0),
+    read BOOLEAN NOT NULL DEFAULT FALSE,
+    read_at TIMESTAMP DEFAULT NULL
+);
+CREATE INDEX notifications_unread ON notifications(node)
+WHERE NOT read;
+/* Web */
+CREATE TABLE web_users (
+    id INTEGER PRIMARY KEY,
+    username VARCHAR(16) NOT NULL CHECK (LENGTH(username) BETWEEN 1 AND 16),
+    password VARCHAR(16) NOT NULL CHECK (LENGTH(password) BETWEEN 1 AND 16),
+    is_admin BOOLEAN NOT NULL DEFAULT FALSE
+);
+CREATE TABLE web_sessions(
+    id INTEGER PRIMARY KEY,
+    "user" INT2 NOT NULL REFERENCES web_users(id),
+    token VARCHAR(16) NOT NULL CHECK (LENGTH(token) BETWEEN 1 AND 16),
+    expiry TIMESTAMP UNIQUE NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%f', 'now', '+12 hours'))
+);
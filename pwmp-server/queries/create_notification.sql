@@ -1,2 +1,27 @@
-INSERT INTO notifications(node, content)
-VALUES ($1, $2);
\ No newline at end of file
+WITH existing AS (
+    UPDATE notifications
+    SET occurrences = occurrences + 1,
+        last_seen = NOW(),
+        expires = NOW() + ($5::INT4 * INTERVAL '1 second')
+    WHERE $6
+        AND node = $1
+        AND severity = $2
+        AND category = $3
+        AND content = $4
+        AND NOT read
+        AND (
+            expires IS NULL
+            OR expires > NOW()
+        )
+    RETURNING id
+)
+INSERT INTO notifications(node, severity, category, content, expires)
+SELECT $1,
+    $2,
+    $3,
+    $4,
+    NOW() + ($5::INT4 * INTERVAL '1 second')
+WHERE NOT EXISTS (
+        SELECT 1
+        FROM existing
+    );
\ No newline at end of file
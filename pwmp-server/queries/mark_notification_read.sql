@@ -0,0 +1,5 @@
+UPDATE notifications
+SET read = TRUE,
+    read_at = NOW()
+WHERE id = $1
+    AND NOT read;
\ No newline at end of file
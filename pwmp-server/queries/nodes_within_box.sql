@@ -0,0 +1,26 @@
+SELECT devices.id,
+    devices.mac_address,
+    devices.note,
+    devices.location [1] AS "latitude!",
+    devices.location [0] AS "longitude!",
+    devices.altitude,
+    NULL::FLOAT8 AS "distance",
+    latest."when" AS "when?",
+    latest.temperature AS "temperature?",
+    latest.humidity AS "humidity?",
+    latest.air_pressure
+FROM devices
+    LEFT JOIN LATERAL (
+        SELECT measurements."when",
+            measurements.temperature,
+            measurements.humidity,
+            measurements.air_pressure
+        FROM measurements
+        WHERE measurements.node = devices.id
+        ORDER BY measurements."when" DESC
+        LIMIT 1
+    ) latest ON TRUE
+WHERE devices.location IS NOT NULL
+    AND devices.location [1] BETWEEN $1 AND $3
+    AND devices.location [0] BETWEEN $2 AND $4
+ORDER BY devices.id;
\ No newline at end of file
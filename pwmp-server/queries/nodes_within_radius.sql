@@ -0,0 +1,32 @@
+SELECT devices.id,
+    devices.mac_address,
+    devices.note,
+    devices.location [1] AS "latitude!",
+    devices.location [0] AS "longitude!",
+    devices.altitude,
+    distance.km AS "distance",
+    latest."when" AS "when?",
+    latest.temperature AS "temperature?",
+    latest.humidity AS "humidity?",
+    latest.air_pressure
+FROM devices
+    CROSS JOIN LATERAL (
+        SELECT 2 * 6371 * ASIN(
+                SQRT(
+                    POWER(SIN(RADIANS(devices.location [1] - $1) / 2), 2) + COS(RADIANS($1)) * COS(RADIANS(devices.location [1])) * POWER(SIN(RADIANS(devices.location [0] - $2) / 2), 2)
+                )
+            ) AS km
+    ) distance
+    LEFT JOIN LATERAL (
+        SELECT measurements."when",
+            measurements.temperature,
+            measurements.humidity,
+            measurements.air_pressure
+        FROM measurements
+        WHERE measurements.node = devices.id
+        ORDER BY measurements."when" DESC
+        LIMIT 1
+    ) latest ON TRUE
+WHERE devices.location IS NOT NULL
+    AND distance.km <= $3
+ORDER BY distance.km;
\ No newline at end of file
@@ -0,0 +1,10 @@
+INSERT INTO measurements(
+        "node",
+        "when",
+        "temperature",
+        "humidity",
+        "air_pressure"
+    )
+VALUES ($1, to_timestamp($2::INT8)::TIMESTAMP, $3, $4, $5)
+ON CONFLICT ("node", "when") DO NOTHING
+RETURNING id;
\ No newline at end of file
@@ -0,0 +1,2 @@
+DELETE FROM notifications
+WHERE expires <= NOW();
\ No newline at end of file
@@ -0,0 +1,4 @@
+UPDATE devices
+SET location = point($3, $2),
+    altitude = $4
+WHERE id = $1;
\ No newline at end of file
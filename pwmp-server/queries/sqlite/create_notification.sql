@@ -0,0 +1,8 @@
+INSERT INTO notifications(node, severity, category, content, expires)
+VALUES (
+        ?1,
+        ?2,
+        ?3,
+        ?4,
+        STRFTIME('%Y-%m-%d %H:%M:%f', 'now', ?5 || ' seconds')
+    );
\ No newline at end of file
@@ -0,0 +1,13 @@
+UPDATE notifications
+SET occurrences = occurrences + 1,
+    last_seen = STRFTIME('%Y-%m-%d %H:%M:%f', 'now'),
+    expires = STRFTIME('%Y-%m-%d %H:%M:%f', 'now', ?5 || ' seconds')
+WHERE node = ?1
+    AND severity = ?2
+    AND category = ?3
+    AND content = ?4
+    AND NOT read
+    AND (
+        expires IS NULL
+        OR expires > STRFTIME('%Y-%m-%d %H:%M:%f', 'now')
+    );
\ No newline at end of file
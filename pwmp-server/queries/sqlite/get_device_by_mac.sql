@@ -0,0 +1,3 @@
+SELECT devices.id
+FROM devices
+WHERE mac_address = ?1;
\ No newline at end of file
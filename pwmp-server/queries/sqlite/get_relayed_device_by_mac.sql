@@ -0,0 +1,4 @@
+SELECT devices.id
+FROM devices
+WHERE mac_address = ?1
+    AND gateway = ?2;
\ No newline at end of file
@@ -0,0 +1,20 @@
+SELECT notifications.id,
+    devices.mac_address,
+    notifications."when",
+    notifications.last_seen,
+    notifications.severity,
+    notifications.category,
+    notifications.content,
+    notifications.occurrences,
+    notifications.read
+FROM notifications
+    JOIN devices ON devices.id = notifications.node
+WHERE (
+        NOT ?1
+        OR NOT notifications.read
+    )
+    AND (
+        notifications.expires IS NULL
+        OR notifications.expires > STRFTIME('%Y-%m-%d %H:%M:%f', 'now')
+    )
+ORDER BY notifications.last_seen DESC;
\ No newline at end of file
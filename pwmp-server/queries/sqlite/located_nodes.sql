@@ -0,0 +1,21 @@
+SELECT devices.id,
+    devices.mac_address,
+    devices.note,
+    devices.latitude,
+    devices.longitude,
+    devices.altitude,
+    latest."when",
+    latest.temperature,
+    latest.humidity,
+    latest.air_pressure
+FROM devices
+    LEFT JOIN measurements latest ON latest.id = (
+        SELECT measurements.id
+        FROM measurements
+        WHERE measurements.node = devices.id
+        ORDER BY measurements."when" DESC
+        LIMIT 1
+    )
+WHERE devices.latitude IS NOT NULL
+    AND devices.longitude IS NOT NULL
+ORDER BY devices.id;
\ No newline at end of file
@@ -0,0 +1,5 @@
+UPDATE notifications
+SET read = TRUE,
+    read_at = STRFTIME('%Y-%m-%d %H:%M:%f', 'now')
+WHERE id = ?1
+    AND NOT read;
\ No newline at end of file
@@ -0,0 +1,16 @@
+INSERT INTO measurements(
+        "node",
+        "when",
+        "temperature",
+        "humidity",
+        "air_pressure"
+    )
+VALUES (
+        ?1,
+        STRFTIME('%Y-%m-%d %H:%M:%f', ?2, 'unixepoch'),
+        ?3,
+        ?4,
+        ?5
+    )
+ON CONFLICT ("node", "when") DO NOTHING
+RETURNING id;
\ No newline at end of file
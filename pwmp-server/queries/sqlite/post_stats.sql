@@ -0,0 +1,7 @@
+INSERT INTO statistics(
+        "measurement",
+        "battery",
+        "wifi_ssid",
+        "wifi_rssi"
+    )
+VALUES (?1, ?2, ?3, ?4);
\ No newline at end of file
@@ -0,0 +1,2 @@
+DELETE FROM notifications
+WHERE expires <= STRFTIME('%Y-%m-%d %H:%M:%f', 'now');
\ No newline at end of file
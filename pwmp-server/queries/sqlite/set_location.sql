@@ -0,0 +1,5 @@
+UPDATE devices
+SET latitude = ?2,
+    longitude = ?3,
+    altitude = ?4
+WHERE id = ?1;
\ No newline at end of file
@@ -0,0 +1,141 @@
+use log::{error, info, warn};
+use pwmp_types::mac::Mac;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+};
+use std::{fs, io, path::Path, process::exit, str::FromStr};
+
+const CA_NAME: &str = "PixelWeather Lab CA";
+
+#[derive(Debug, thiserror::Error)]
+enum CertError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Rcgen(#[from] rcgen::Error),
+}
+
+/// A certificate authority used to sign the server and node certificates.
+struct Authority {
+    cert: Certificate,
+    key: KeyPair,
+}
+
+/// Generate a self-signed CA, a server certificate and certificates for the specified nodes.
+/// An existing CA in `out` is reused, so nodes can be added later.
+pub fn cert_main(out: &Path, hosts: Vec<String>, nodes: &[String]) {
+    let mut macs = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let Ok(mac) = Mac::from_str(node) else {
+            error!("Invalid MAC address format: {node}");
+            exit(1);
+        };
+        macs.push(mac);
+    }
+
+    if let Err(why) = generate(out, hosts, &macs) {
+        error!("Failed to generate certificates: {why}");
+        exit(1);
+    }
+
+    info!("Certificates stored in {}", out.display());
+}
+
+fn generate(out: &Path, hosts: Vec<String>, macs: &[Mac]) -> Result<(), CertError> {
+    fs::create_dir_all(out)?;
+
+    let ca = Authority::open(out)?;
+
+    if out.join("server.pem").exists() {
+        warn!("Server certificate already exists, not replacing it");
+    } else {
+        let mut params = CertificateParams::new(hosts)?;
+        params.distinguished_name = name("PixelWeather Server");
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+
+        ca.issue(params, out, "server")?;
+    }
+
+    for mac in macs {
+        let mut params = CertificateParams::default();
+        params.distinguished_name = name(&mac.to_string());
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+
+        ca.issue(
+            params,
+            out,
+            &format!("node-{}", mac.to_string().replace(':', "-")),
+        )?;
+    }
+
+    Ok(())
+}
+
+impl Authority {
+    /// Load the CA key from `out`, or create a new CA if there is none.
+    fn open(out: &Path) -> Result<Self, CertError> {
+        let key_path = out.join("ca.key");
+
+        let key = if key_path.exists() {
+            info!("Using existing CA from {}", key_path.display());
+            KeyPair::from_pem(&fs::read_to_string(&key_path)?)?
+        } else {
+            let key = KeyPair::generate()?;
+            write_key(&key_path, &key)?;
+            key
+        };
+
+        // Signing only needs the name and key of the CA, so the certificate is recreated
+        // instead of being parsed.
+        let cert = Self::params().self_signed(&key)?;
+
+        let cert_path = out.join("ca.pem");
+        if !cert_path.exists() {
+            fs::write(&cert_path, cert.pem())?;
+            info!("Created {}", cert_path.display());
+        }
+
+        Ok(Self { cert, key })
+    }
+
+    fn params() -> CertificateParams {
+        let mut params = CertificateParams::default();
+        params.distinguished_name = name(CA_NAME);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+
+        params
+    }
+
+    /// Sign a new certificate and store it as `<file>.pem` with its key in `<file>.key`.
+    fn issue(&self, params: CertificateParams, out: &Path, file: &str) -> Result<(), CertError> {
+        let key = KeyPair::generate()?;
+        let cert = params.signed_by(&key, &self.cert, &self.key)?;
+
+        let cert_path = out.join(format!("{file}.pem"));
+        fs::write(&cert_path, cert.pem())?;
+        write_key(&out.join(format!("{file}.key")), &key)?;
+
+        info!("Created {}", cert_path.display());
+        Ok(())
+    }
+}
+
+fn name(common_name: &str) -> DistinguishedName {
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, common_name);
+
+    name
+}
+
+/// Store a private key, readable only by the owner.
+fn write_key(path: &Path, key: &KeyPair) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    io::Write::write_all(&mut options.open(path)?, key.serialize_pem().as_bytes())
+}
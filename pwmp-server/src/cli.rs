@@ -1,4 +1,5 @@
-use clap::{command, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use pwmp_types::aliases::{AirPressure, BatteryVoltage, Humidity, Rssi, Temperature};
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
@@ -8,6 +9,10 @@ pub struct Cli {
     #[arg(long, value_name = "PATH")]
     pub config: Option<PathBuf>,
 
+    /// Keep all data in memory and register demo devices, no database needed
+    #[arg(long)]
+    pub demo: bool,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -19,17 +24,126 @@ pub enum Command {
         #[command(subcommand)]
         command: ServiceCommand,
     },
+    /// Notification management
+    Notifications {
+        #[command(subcommand)]
+        command: NotificationCommand,
+    },
+    /// Node location management
+    Location {
+        #[command(subcommand)]
+        command: LocationCommand,
+    },
+    /// Database schema management
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
     /// Test connection to a PWMP server
     Test {
-        /// Host to connect to
+        /// Host to connect to, or `auto` to discover a server on the local network
         host: String,
         /// MAC address to authenticate with
         mac: String,
         /// Alternative port to use
         port: Option<u16>,
+        /// Connect using TLS, trusting only the server certificate stored at PATH
+        #[arg(long, value_name = "PATH")]
+        pin: Option<PathBuf>,
+    },
+    /// Emulate a node, running a full session for every reading
+    Emulate(EmulateArgs),
+    /// Simulate a fleet of nodes posting synthetic weather data
+    Simulate(SimulateArgs),
+    /// Generate a self-signed CA, a server certificate and node certificates for TLS
+    Cert {
+        /// Directory to store the certificates in. An existing CA in it is reused.
+        #[arg(long, value_name = "PATH", default_value = ".")]
+        out: PathBuf,
+        /// Host name or IP address the server certificate is valid for
+        #[arg(long = "host", value_name = "HOST", default_value = "localhost")]
+        hosts: Vec<String>,
+        /// MAC addresses of nodes to issue certificates for
+        nodes: Vec<String>,
     },
 }
 
+#[derive(Debug, Args)]
+#[command(allow_negative_numbers = true)]
+pub struct EmulateArgs {
+    /// Host to connect to
+    pub host: String,
+    /// MAC address of the emulated node
+    pub mac: String,
+    /// Alternative port to use
+    pub port: Option<u16>,
+    /// Connect using TLS, trusting only the server certificate stored at PATH
+    #[arg(long, value_name = "PATH")]
+    pub pin: Option<PathBuf>,
+    /// Read readings from a CSV or JSON file. Readings with a timestamp are posted as
+    /// measurements taken at that time.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["temperature", "humidity", "air_pressure", "battery"]
+    )]
+    pub file: Option<PathBuf>,
+    /// Temperature in degrees Celsius
+    #[arg(long, required_unless_present = "file")]
+    pub temperature: Option<Temperature>,
+    /// Relative humidity in percent
+    #[arg(long, required_unless_present = "file")]
+    pub humidity: Option<Humidity>,
+    /// Air pressure in hPa
+    #[arg(long)]
+    pub air_pressure: Option<AirPressure>,
+    /// Battery voltage. Stats are only posted if it's known.
+    #[arg(long)]
+    pub battery: Option<BatteryVoltage>,
+    /// ESSID of the wireless network, unless specified by a reading
+    #[arg(long, default_value = "emulator")]
+    pub ssid: String,
+    /// RSSI of the wireless connection, unless specified by a reading
+    #[arg(long, default_value_t = -50)]
+    pub rssi: Rssi,
+    /// Send a notification with this content in every session
+    #[arg(long, value_name = "TEXT")]
+    pub notify: Option<String>,
+    /// Seconds to wait between sessions
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0)]
+    pub interval: f64,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Host to connect to
+    pub host: String,
+    /// Alternative port to use
+    pub port: Option<u16>,
+    /// Connect using TLS, trusting only the server certificate stored at PATH
+    #[arg(long, value_name = "PATH")]
+    pub pin: Option<PathBuf>,
+    /// Number of virtual nodes
+    #[arg(long, short, default_value_t = 10)]
+    pub nodes: usize,
+    /// MAC address of the first node. Other nodes use the following addresses.
+    #[arg(long, value_name = "MAC", default_value = "02:00:00:00:00:01")]
+    pub first_mac: String,
+    /// How long to run the simulation for, in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub duration: u64,
+    /// Spread the first wakeups of the nodes over this many seconds. By default, all nodes
+    /// wake up at the same time.
+    #[arg(long, value_name = "SECONDS", default_value_t = 0)]
+    pub stagger: u64,
+    /// Run the simulated clock this many times faster. Sleep times are shortened accordingly.
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    pub time_scale: f64,
+    /// Seed of the synthetic weather
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
 #[derive(Debug, Subcommand, Clone, Copy)]
 pub enum ServiceCommand {
     /// Start the service
@@ -49,3 +163,72 @@ pub enum ServiceCommand {
     /// Reinstall service
     Reinstall,
 }
+
+#[derive(Debug, Subcommand, Clone, Copy)]
+pub enum NotificationCommand {
+    /// List active notifications
+    List {
+        /// Also list notifications that were already read
+        #[arg(long)]
+        all: bool,
+    },
+    /// Mark a notification as read
+    Read {
+        /// ID of the notification
+        id: i32,
+    },
+    /// Delete expired notifications
+    Purge,
+}
+
+#[derive(Debug, Subcommand, Clone, Copy)]
+pub enum DbCommand {
+    /// Apply pending migrations
+    Migrate,
+    /// Show the schema version and which migrations were applied
+    Status,
+    /// Delete all tables and data, then set up the schema again
+    Reset {
+        /// Confirm deleting all data
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum LocationCommand {
+    /// Manually set the location of a node
+    #[command(allow_negative_numbers = true)]
+    Set {
+        /// MAC address of the node
+        mac: String,
+        /// Latitude in degrees
+        latitude: f64,
+        /// Longitude in degrees
+        longitude: f64,
+        /// Altitude above sea level in meters
+        altitude: Option<i16>,
+    },
+    /// List nodes within a radius around a point
+    #[command(allow_negative_numbers = true)]
+    Near {
+        /// Latitude of the center in degrees
+        latitude: f64,
+        /// Longitude of the center in degrees
+        longitude: f64,
+        /// Radius in kilometers
+        radius: f64,
+    },
+    /// List nodes within a bounding box
+    #[command(allow_negative_numbers = true)]
+    Within {
+        /// Southern edge in degrees
+        min_latitude: f64,
+        /// Western edge in degrees
+        min_longitude: f64,
+        /// Northern edge in degrees
+        max_latitude: f64,
+        /// Eastern edge in degrees
+        max_longitude: f64,
+    },
+}
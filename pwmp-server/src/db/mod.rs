@@ -0,0 +1,97 @@
+use crate::{
+    cli::DbCommand,
+    server::{
+        config::DatabaseBackend,
+        storage::{postgres::DatabaseClient, schema::Schema, sqlite::SqliteClient},
+    },
+    CONFIG,
+};
+use log::{error, info};
+use std::process::exit;
+
+pub async fn db_main(cmd: DbCommand) {
+    let db = connect().await;
+
+    match cmd {
+        DbCommand::Migrate => match db.migrate().await {
+            Ok(0) => info!("Schema is up to date"),
+            Ok(applied) => info!("Applied {applied} migrations"),
+            Err(why) => {
+                error!("Failed to migrate database: {why}");
+                exit(1);
+            }
+        },
+        DbCommand::Status => {
+            let status = match db.schema_status().await {
+                Ok(status) => status,
+                Err(why) => {
+                    error!("Failed to read schema version: {why}");
+                    exit(1);
+                }
+            };
+
+            for (migration, applied) in &status.migrations {
+                let state = if status.modified.contains(&migration.version) {
+                    "modified"
+                } else if *applied {
+                    "applied"
+                } else {
+                    "pending"
+                };
+
+                println!("#{} [{state}] {}", migration.version, migration.description);
+            }
+
+            for version in &status.unknown {
+                println!("#{version} [unknown]");
+            }
+
+            if let Err(why) = status.check_current() {
+                error!("{why}");
+                exit(1);
+            }
+
+            info!("Schema version {} is up to date", status.latest());
+        }
+        DbCommand::Reset { yes: false } => {
+            error!("This deletes all data, pass --yes to confirm");
+            exit(1);
+        }
+        DbCommand::Reset { yes: true } => match db.reset().await {
+            Ok(()) => info!("Database reset"),
+            Err(why) => {
+                error!("Failed to reset database: {why}");
+                exit(1);
+            }
+        },
+    }
+}
+
+async fn connect() -> Box<dyn Schema> {
+    let result = match CONFIG.database.backend {
+        DatabaseBackend::Postgres => {
+            info!("Connecting to database at {}", CONFIG.database.host);
+            DatabaseClient::connect(&CONFIG)
+                .await
+                .map(|db| Box::new(db) as Box<dyn Schema>)
+        }
+        DatabaseBackend::Sqlite => {
+            info!("Opening database {}", CONFIG.database.path.display());
+            SqliteClient::connect(&CONFIG)
+                .await
+                .map(|db| Box::new(db) as Box<dyn Schema>)
+        }
+        DatabaseBackend::Memory => {
+            error!("The memory backend has no database schema");
+            exit(1);
+        }
+    };
+
+    match result {
+        Ok(db) => db,
+        Err(why) => {
+            error!("Failed to connect to database: {why}");
+            exit(1);
+        }
+    }
+}
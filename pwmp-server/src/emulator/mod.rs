@@ -0,0 +1,159 @@
+use crate::cli::EmulateArgs;
+use log::{debug, error, info, warn};
+use pwmp_client::{
+    error::Error,
+    outbox::{DropPolicy, MemoryStorage, Outbox, QueuedMeasurement, QueuedStats},
+    report::SessionReport,
+    tls::{
+        pki_types::{pem::PemObject, CertificateDer},
+        TlsOptions,
+    },
+    transport::Transport,
+    PwmpClient,
+};
+use pwmp_types::{
+    mac::Mac,
+    notification::{NotificationCategory, NotificationSeverity},
+};
+use readings::Reading;
+use std::{process::exit, str::FromStr, thread, time::Duration};
+
+mod readings;
+
+/// Emulate a node by running a full session for every reading, like a node that wakes up,
+/// posts it's measurements and goes back to sleep.
+#[allow(clippy::needless_pass_by_value)]
+pub fn emulate_main(args: EmulateArgs) {
+    let Ok(mac) = Mac::from_str(&args.mac) else {
+        error!("Invalid MAC address format");
+        exit(1);
+    };
+
+    let Ok(interval) = Duration::try_from_secs_f64(args.interval) else {
+        error!("Invalid interval {}", args.interval);
+        exit(1);
+    };
+
+    let pin = args.pin.as_deref().map(|path| {
+        CertificateDer::from_pem_file(path).unwrap_or_else(|why| {
+            error!("Failed to read {}: {why}", path.display());
+            exit(1);
+        })
+    });
+
+    let readings = match (&args.file, args.temperature, args.humidity) {
+        (Some(path), ..) => readings::load(path).unwrap_or_else(|why| {
+            error!("Failed to read {}: {why}", path.display());
+            exit(1);
+        }),
+        (None, Some(temperature), Some(humidity)) => vec![Reading {
+            timestamp: None,
+            temperature,
+            humidity,
+            air_pressure: args.air_pressure,
+            battery: args.battery,
+            wifi_ssid: None,
+            wifi_rssi: None,
+            notification: None,
+        }],
+        _ => unreachable!("Either a file or values are required by the CLI"),
+    };
+
+    let addr = format!("{}:{}", args.host, args.port.unwrap_or(55300));
+    let mut failed = 0;
+
+    for (i, reading) in readings.iter().enumerate() {
+        if i != 0 {
+            thread::sleep(interval);
+        }
+
+        let result = match &pin {
+            Some(cert) => PwmpClient::builder(mac.clone())
+                .build_tls(addr.as_str(), TlsOptions::pinned(cert.clone()))
+                .and_then(|client| run_session(client, reading, &args)),
+            None => PwmpClient::new(addr.as_str(), mac.clone())
+                .and_then(|client| run_session(client, reading, &args)),
+        };
+
+        match result {
+            Ok(report) => info!(
+                "Session {}/{} took {:?} ({:?} connecting), sent {} and received {} bytes",
+                i + 1,
+                readings.len(),
+                report.total_time(),
+                report.connect_time + report.handshake_time,
+                report.bytes_sent,
+                report.bytes_received
+            ),
+            Err(why) => {
+                error!("Session {}/{} failed: {why}", i + 1, readings.len());
+                failed += 1;
+            }
+        }
+    }
+
+    if failed != 0 {
+        error!("{failed} of {} sessions failed", readings.len());
+        exit(1);
+    }
+
+    info!("Posted {} readings as {mac}", readings.len());
+}
+
+/// Run a session the way a node does: fetch settings, post the reading and stats,
+/// optionally send a notification and say `Bye`.
+fn run_session<T: Transport>(
+    mut client: PwmpClient<T>,
+    reading: &Reading,
+    args: &EmulateArgs,
+) -> Result<SessionReport, Error> {
+    let settings = client.fetch_node_settings()?;
+    debug!("Settings: {settings:?}");
+
+    let wifi_ssid = reading.wifi_ssid.as_deref().unwrap_or(&args.ssid);
+    let wifi_rssi = reading.wifi_rssi.unwrap_or(args.rssi);
+
+    match reading.timestamp {
+        // Readings from the past are posted like measurements that were queued by the node.
+        Some(measured_at) => {
+            let mut outbox = Outbox::new(MemoryStorage::default(), 1, DropPolicy::DropOldest)?;
+            outbox.push(QueuedMeasurement {
+                measured_at,
+                temperature: reading.temperature,
+                humidity: reading.humidity,
+                air_pressure: reading.air_pressure,
+                stats: reading.battery.map(|battery| QueuedStats {
+                    battery,
+                    wifi_ssid: wifi_ssid.into(),
+                    wifi_rssi,
+                }),
+            })?;
+
+            if client.attach_outbox(outbox)? == 0 {
+                warn!("The server rejected the reading taken at {measured_at}");
+            }
+        }
+        None => {
+            client.post_measurements(
+                reading.temperature,
+                reading.humidity,
+                reading.air_pressure,
+            )?;
+
+            if let Some(battery) = reading.battery {
+                client.post_stats(battery, wifi_ssid, wifi_rssi)?;
+            }
+        }
+    }
+
+    if let Some(content) = reading.notification.as_deref().or(args.notify.as_deref()) {
+        client.send_notification(
+            content,
+            NotificationSeverity::Info,
+            NotificationCategory::General,
+        )?;
+    }
+
+    let (result, report) = client.close_with_report();
+    result.map(|()| report)
+}
@@ -0,0 +1,127 @@
+use pwmp_types::{
+    aliases::{AirPressure, BatteryVoltage, Humidity, Rssi, Temperature, Timestamp},
+    Decimal,
+};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{fs, io, path::Path, str::FromStr};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// A reading of the emulated node.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Reading {
+    /// When the reading was taken. Readings without a timestamp are posted as if they
+    /// were taken right now.
+    #[serde(default, deserialize_with = "timestamp")]
+    pub timestamp: Option<Timestamp>,
+    #[serde(deserialize_with = "decimal")]
+    pub temperature: Temperature,
+    pub humidity: Humidity,
+    #[serde(default)]
+    pub air_pressure: Option<AirPressure>,
+    /// Stats are only posted if the battery voltage is known.
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub battery: Option<BatteryVoltage>,
+    #[serde(default)]
+    pub wifi_ssid: Option<String>,
+    #[serde(default)]
+    pub wifi_rssi: Option<Rssi>,
+    /// Content of a notification to send after posting the reading.
+    #[serde(default)]
+    pub notification: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReadingsError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A number which may also be written as a string, for eg. `21.5` or `"21.5"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Float(f64),
+    Text(String),
+}
+
+/// A UNIX timestamp in seconds, or an RFC 3339 date and time.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Time {
+    Unix(Timestamp),
+    Text(String),
+}
+
+/// Load readings from a JSON file containing an array of readings, or from a CSV file
+/// with a header. The format is chosen by the file extension.
+pub fn load(path: &Path) -> Result<Vec<Reading>, ReadingsError> {
+    if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+    {
+        return Ok(serde_json::from_slice(&fs::read(path)?)?);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+
+    Ok(reader.deserialize().collect::<Result<_, _>>()?)
+}
+
+impl Number {
+    fn to_decimal(&self) -> Option<Decimal> {
+        let decimal = match self {
+            // The shortest representation of the float is what was written in the file.
+            Self::Float(value) => Decimal::from_str(&value.to_string()).ok()?,
+            Self::Text(text) => Decimal::from_str(text.trim()).ok()?,
+        };
+
+        Some(decimal.round_dp(2))
+    }
+}
+
+impl Time {
+    fn to_timestamp(&self) -> Option<Timestamp> {
+        match self {
+            Self::Unix(timestamp) => Some(*timestamp),
+            Self::Text(text) => OffsetDateTime::parse(text.trim(), &Rfc3339)
+                .ok()
+                .and_then(|time| Timestamp::try_from(time.unix_timestamp()).ok()),
+        }
+    }
+}
+
+fn decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+    Number::deserialize(deserializer)?
+        .to_decimal()
+        .ok_or_else(|| D::Error::custom("invalid decimal number"))
+}
+
+fn optional_decimal<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Decimal>, D::Error> {
+    match Option::<Number>::deserialize(deserializer)? {
+        Some(Number::Text(text)) if text.trim().is_empty() => Ok(None),
+        Some(number) => number
+            .to_decimal()
+            .map(Some)
+            .ok_or_else(|| D::Error::custom("invalid decimal number")),
+        None => Ok(None),
+    }
+}
+
+fn timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Timestamp>, D::Error> {
+    match Option::<Time>::deserialize(deserializer)? {
+        Some(Time::Text(text)) if text.trim().is_empty() => Ok(None),
+        Some(time) => time
+            .to_timestamp()
+            .map(Some)
+            .ok_or_else(|| D::Error::custom("expected a UNIX timestamp or an RFC 3339 time")),
+        None => Ok(None),
+    }
+}
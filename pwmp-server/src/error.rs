@@ -1,3 +1,4 @@
+use crate::server::storage::StorageError;
 use std::{fmt::Display, io};
 
 #[derive(Debug, thiserror::Error)]
@@ -12,8 +13,12 @@ pub enum Error {
     BadRequest,
     /// Connection closed unexpectedly
     Quit,
+    /// The node didn't send anything in time
+    Timeout,
     /// Generic I/O error
     Io(#[from] io::Error),
+    /// Failed to access the storage backend
+    Storage(#[from] StorageError),
 }
 
 impl Display for Error {
@@ -27,7 +32,9 @@ impl Display for Error {
             Self::NotHello => write!(f, "Expected a `Hello` request"),
             Self::BadRequest => write!(f, "Malformed or unprocessable request"),
             Self::Quit => write!(f, "Connection closed unexpectedly"),
+            Self::Timeout => write!(f, "Timed out waiting for the node"),
             Self::Io(why) => write!(f, "{why}"),
+            Self::Storage(why) => write!(f, "{why}"),
         }
     }
 }
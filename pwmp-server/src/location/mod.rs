@@ -0,0 +1,107 @@
+use crate::{
+    cli::LocationCommand,
+    server::{connect_storage, storage::NodeLocation},
+};
+use log::{error, info};
+use pwmp_types::mac::Mac;
+use std::{process::exit, str::FromStr};
+
+pub async fn location_main(cmd: LocationCommand) {
+    let db = connect_storage().await;
+
+    let nodes = match cmd {
+        LocationCommand::Set {
+            mac,
+            latitude,
+            longitude,
+            altitude,
+        } => {
+            let Ok(mac) = Mac::from_str(&mac) else {
+                error!("Invalid MAC address format");
+                exit(1);
+            };
+
+            if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                error!("Location {latitude}, {longitude} is out of range");
+                exit(1);
+            }
+
+            let id = match db.authorize_device(&mac).await {
+                Ok(Some(id)) => id,
+                Ok(None) => {
+                    error!("Device {mac} does not exist");
+                    exit(1);
+                }
+                Err(why) => {
+                    error!("Failed to look up device: {why}");
+                    exit(1);
+                }
+            };
+
+            if let Err(why) = db.set_location(id, latitude, longitude, altitude).await {
+                error!("Failed to set location: {why}");
+                exit(1);
+            }
+
+            info!("Location of node #{id} set to {latitude}, {longitude}");
+            return;
+        }
+        LocationCommand::Near {
+            latitude,
+            longitude,
+            radius,
+        } => db.nodes_within_radius(latitude, longitude, radius).await,
+        LocationCommand::Within {
+            min_latitude,
+            min_longitude,
+            max_latitude,
+            max_longitude,
+        } => {
+            db.nodes_within_box((min_latitude, min_longitude), (max_latitude, max_longitude))
+                .await
+        }
+    };
+
+    match nodes {
+        Ok(nodes) if nodes.is_empty() => info!("No nodes found"),
+        Ok(nodes) => nodes.iter().for_each(print_node),
+        Err(why) => {
+            error!("Failed to query nodes: {why}");
+            exit(1);
+        }
+    }
+}
+
+fn print_node(node: &NodeLocation) {
+    let mut line = format!(
+        "#{} {} at {}, {}",
+        node.id, node.mac_address, node.latitude, node.longitude
+    );
+
+    if let Some(altitude) = node.altitude {
+        line.push_str(&format!(" ({altitude}m)"));
+    }
+
+    if let Some(distance) = node.distance {
+        line.push_str(&format!(", {distance:.2}km away"));
+    }
+
+    if let Some(note) = &node.note {
+        line.push_str(&format!(" \"{note}\""));
+    }
+
+    match (node.when, node.temperature, node.humidity) {
+        (Some(when), Some(temperature), Some(humidity)) => {
+            line.push_str(&format!(" - {temperature}C, {humidity}%"));
+
+            if let Some(air_pressure) = node.air_pressure {
+                line.push_str(&format!(", {air_pressure}hPa"));
+            }
+
+            line.push_str(&format!(" at {when}"));
+        }
+        _ => line.push_str(" - no measurements"),
+    }
+
+    println!("{line}");
+}
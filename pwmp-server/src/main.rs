@@ -5,20 +5,36 @@
     clippy::module_name_repetitions
 )]
 use crate::{
+    cert::cert_main,
     cli::Command,
-    server::{config::Config, server_main},
+    db::db_main,
+    emulator::emulate_main,
+    location::location_main,
+    notifications::notifications_main,
+    server::{
+        config::{Config, DatabaseBackend},
+        server_main,
+    },
+    simulator::simulate_main,
     svcmgr::svcmgr_main,
 };
 use always_cell::AlwaysCell;
 use clap::Parser;
 use log::{debug, error, info};
 use simple_logger::SimpleLogger;
-use std::process::exit;
+use std::{future::Future, process::exit};
 use time::macros::format_description;
+use tokio::runtime::Runtime;
 
+mod cert;
 mod cli;
+mod db;
+mod emulator;
 mod error;
+mod location;
+mod notifications;
 mod server;
+mod simulator;
 mod svcmgr;
 mod tester;
 
@@ -36,26 +52,64 @@ fn main() {
     #[cfg(debug_assertions)]
     let logger = logger.with_level(log::LevelFilter::Debug);
 
+    // Checking the schema makes PostgreSQL report that the migrations table already exists.
+    let logger = logger.with_module_level("sqlx::postgres::notice", log::LevelFilter::Warn);
+
     logger.init().unwrap();
 
     info!("PixelWeather Server v{}", env!("CARGO_PKG_VERSION"));
     debug!("Arguments: {args:?}");
 
-    let config_path = args.config.unwrap_or_else(Config::default_path);
-    info!("Loading config from {}", config_path.display());
+    let mut config: Config = match args.config {
+        // The demo runs without a configuration file unless one is given.
+        None if args.demo => Config::default(),
+        config_path => {
+            let config_path = config_path.unwrap_or_else(Config::default_path);
+            info!("Loading config from {}", config_path.display());
 
-    let config: Config = match confy::load_path(config_path) {
-        Ok(config) => config,
-        Err(why) => {
-            error!("Failed to load configuration: {why}");
-            exit(1);
+            match confy::load_path(config_path) {
+                Ok(config) => config,
+                Err(why) => {
+                    error!("Failed to load configuration: {why}");
+                    exit(1);
+                }
+            }
         }
     };
+
+    if args.demo {
+        info!("Running in demo mode");
+        config.database.backend = DatabaseBackend::Memory;
+    }
+
     AlwaysCell::<Config>::set(&CONFIG, config);
 
     match args.command {
         Some(Command::Service { command }) => svcmgr_main(command),
-        Some(Command::Test { host, mac, port }) => tester::test(host, port, mac),
-        None => server_main(),
+        Some(Command::Notifications { command }) => block_on(notifications_main(command)),
+        Some(Command::Location { command }) => block_on(location_main(command)),
+        Some(Command::Db { command }) => block_on(db_main(command)),
+        Some(Command::Test {
+            host,
+            mac,
+            port,
+            pin,
+        }) => tester::test(host, port, mac, pin.as_deref()),
+        Some(Command::Emulate(args)) => emulate_main(args),
+        Some(Command::Simulate(args)) => simulate_main(args),
+        Some(Command::Cert { out, hosts, nodes }) => cert_main(&out, hosts, &nodes),
+        None => block_on(server_main()),
     }
 }
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let runtime = match Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(why) => {
+            error!("Failed to start async runtime: {why}");
+            exit(1);
+        }
+    };
+
+    runtime.block_on(future)
+}
@@ -0,0 +1,54 @@
+use crate::{cli::NotificationCommand, server::connect_storage};
+use log::{error, info, warn};
+use std::process::exit;
+
+pub async fn notifications_main(cmd: NotificationCommand) {
+    let db = connect_storage().await;
+
+    match cmd {
+        NotificationCommand::List { all } => {
+            let notifications = match db.list_notifications(!all).await {
+                Ok(notifications) => notifications,
+                Err(why) => {
+                    error!("Failed to list notifications: {why}");
+                    exit(1);
+                }
+            };
+
+            if notifications.is_empty() {
+                info!("No notifications");
+                return;
+            }
+
+            for notification in notifications {
+                println!(
+                    "#{} [{}] {}/{} from {} at {} (seen {}x, last at {}): {}",
+                    notification.id,
+                    if notification.read { "read" } else { "unread" },
+                    notification.severity,
+                    notification.category,
+                    notification.mac_address,
+                    notification.when,
+                    notification.occurrences,
+                    notification.last_seen,
+                    notification.content
+                );
+            }
+        }
+        NotificationCommand::Read { id } => match db.mark_notification_read(id).await {
+            Ok(true) => info!("Notification #{id} marked as read"),
+            Ok(false) => warn!("Notification #{id} does not exist or was already read"),
+            Err(why) => {
+                error!("Failed to mark notification as read: {why}");
+                exit(1);
+            }
+        },
+        NotificationCommand::Purge => match db.purge_notifications().await {
+            Ok(purged) => info!("Purged {purged} expired notifications"),
+            Err(why) => {
+                error!("Failed to purge notifications: {why}");
+                exit(1);
+            }
+        },
+    }
+}
@@ -1,34 +1,58 @@
-use crate::error::Error;
+use super::tls::{self, Stream};
+use crate::{error::Error, CONFIG};
 use log::debug;
-use pwmp_types::{mac::Mac, request::Request, response::Response, Message, NodeId};
-use std::{
-    io::{Read, Write},
-    net::{SocketAddr, TcpStream},
+use pwmp_types::{
+    aliases::SessionToken,
+    borrowed::{MessageRef, RequestRef},
+    mac::Mac,
+    response::Response,
+    Message, NodeId,
+};
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    time::timeout,
 };
 
 const RCV_BUFFER_SIZE: usize = 128;
 type Result<T> = ::std::result::Result<T, Error>;
 
+/// Name on the certificate a node authenticated with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateName<'a> {
+    /// Client certificates are not required.
+    NotRequired,
+    /// Common name in the certificate's subject.
+    Common(&'a str),
+    /// The certificate has no common name.
+    Missing,
+}
+
 #[derive(Debug)]
 pub struct Client {
     id: Option<NodeId>,
     mac: Option<Mac>,
-    socket: TcpStream,
+    resume: Option<SessionToken>,
+    socket: Stream,
     buf: [u8; RCV_BUFFER_SIZE],
 }
 
 impl Client {
-    pub fn new(socket: TcpStream) -> Result<Self> {
+    pub async fn new(socket: Stream) -> Result<Self> {
         let mut client = Self {
             id: None,
             mac: None,
+            resume: None,
             socket,
             buf: [0; RCV_BUFFER_SIZE],
         };
 
         debug!("{}: Awaiting greeting", client.peer_addr_str());
-        let mac = client.handle_hello()?;
+        let (mac, resume) = timeout(CONFIG.timeouts.hello(), client.handle_hello())
+            .await
+            .map_err(|_| Error::Timeout)??;
         client.mac = Some(mac);
+        client.resume = resume;
         debug!("{}: Is {}?", client.peer_addr_str(), client.mac());
 
         Ok(client)
@@ -47,6 +71,19 @@ impl Client {
         self.mac.as_ref().unwrap()
     }
 
+    pub const fn resume_token(&self) -> Option<SessionToken> {
+        self.resume
+    }
+
+    /// Returns the common name of the node's certificate.
+    pub fn certificate_name(&self) -> CertificateName<'_> {
+        let Some(cert) = self.socket.peer_certificate() else {
+            return CertificateName::NotRequired;
+        };
+
+        tls::common_name(cert).map_or(CertificateName::Missing, CertificateName::Common)
+    }
+
     pub fn peer_addr(&self) -> Option<SocketAddr> {
         self.socket.peer_addr().ok()
     }
@@ -56,7 +93,7 @@ impl Client {
             .map_or_else(|| "Unknown".to_string(), |addr| addr.to_string())
     }
 
-    pub fn send_response(&mut self, resp: Response) -> Result<()> {
+    pub async fn send_response(&mut self, resp: Response) -> Result<()> {
         let message = Message::Response(resp);
         debug!(
             "{}: responding with {:?} ({} bytes)",
@@ -65,34 +102,43 @@ impl Client {
             message.response().unwrap(),
             message.size()
         );
-        self.socket.write_all(&message.to_raw())?;
-        self.socket.flush()?;
+        self.socket.write_all(&message.to_raw()).await?;
+        self.socket.flush().await?;
 
         Ok(())
     }
 
-    fn handle_hello(&mut self) -> Result<Mac> {
-        let req = self.await_request()?;
-        let Request::Hello { mac } = req else {
-            return Err(Error::NotHello);
-        };
+    /// Close the connection right away, so the node doesn't have to wait until the session
+    /// is cleaned up.
+    pub async fn shutdown(&mut self) {
+        self.socket.shutdown().await;
+    }
 
-        Ok(mac)
+    async fn handle_hello(&mut self) -> Result<(Mac, Option<SessionToken>)> {
+        match self.await_request().await? {
+            RequestRef::Hello { mac } => Ok((mac, None)),
+            RequestRef::Resume { mac, session } => Ok((mac, Some(session))),
+            _ => Err(Error::NotHello),
+        }
     }
 
-    fn await_next_message(&mut self) -> Result<Message> {
-        let read = self.socket.read(&mut self.buf)?;
+    async fn await_next_message(&mut self) -> Result<MessageRef<'_>> {
+        let read = timeout(CONFIG.timeouts.idle(), self.socket.read(&mut self.buf))
+            .await
+            .map_err(|_| Error::Timeout)??;
         if read == 0 {
             return Err(Error::Quit);
         }
 
-        let message = Message::from_raw(&self.buf[..read]).ok_or(Error::MessageParse)?;
+        debug!("{}: Received {read} bytes", self.peer_addr_str());
+        let message = MessageRef::from_raw(&self.buf[..read]).ok_or(Error::MessageParse)?;
 
         Ok(message)
     }
 
-    pub fn await_request(&mut self) -> Result<Request> {
-        self.await_next_message()?
+    pub async fn await_request(&mut self) -> Result<RequestRef<'_>> {
+        self.await_next_message()
+            .await?
             .to_request()
             .ok_or(Error::NotRequest)
     }
@@ -1,151 +1,883 @@
-use super::{client::Client, db::DatabaseClient, rate_limit::RateLimiter};
+use super::{
+    client::{CertificateName, Client},
+    rate_limit::RateLimiter,
+    session::{Lease, SessionState, SessionStore},
+    storage::{Storage, StorageError},
+    tls::Stream,
+};
 use crate::{error::Error, CONFIG};
 use log::{debug, error, warn};
-use pwmp_types::{aliases::MeasurementId, request::Request, response::Response, Message};
+use pwmp_types::{
+    aliases::MeasurementId, borrowed::RequestRef, mac::Mac, notification::MAX_NOTIFICATION_LEN,
+    request::NEW_SESSION, response::Response, setting::SettingName, NodeId,
+};
 use std::{
-    net::TcpStream,
-    panic,
-    sync::{
-        atomic::{AtomicU32, Ordering},
-        Arc,
-    },
-    time::Duration,
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-pub fn handle_client(
-    client: TcpStream,
-    db: &DatabaseClient,
-    connection_count: Arc<AtomicU32>,
+/// Seconds the clock of a node may be ahead of the server's clock.
+const MAX_CLOCK_SKEW: u64 = 60;
+
+pub async fn handle_client(
+    client: Stream,
+    db: &dyn Storage,
+    sessions: &SessionStore,
 ) -> Result<(), Error> {
-    set_panic_hook(connection_count);
-    let mut client = Client::new(client)?;
-    let mut rate_limiter = RateLimiter::new(
-        Duration::from_secs(CONFIG.rate_limits.time_frame),
-        CONFIG.rate_limits.max_requests,
-    );
+    let mut client = Client::new(client).await?;
 
-    if let Some(id) = db.authorize_device(client.mac()) {
+    // Certificates are issued for a single MAC address, nodes can't claim another one.
+    let issued_for = match client.certificate_name() {
+        CertificateName::NotRequired => None,
+        CertificateName::Common(name) if name == client.mac().to_string() => None,
+        CertificateName::Common(name) => Some(name),
+        CertificateName::Missing => Some("an unnamed device"),
+    };
+
+    if let Some(issued_for) = issued_for {
+        warn!(
+            "Device {} presented a certificate issued for {issued_for}",
+            client.mac()
+        );
+        client.send_response(Response::Reject).await?;
+        return Ok(());
+    }
+
+    if let Some(id) = db.authorize_device(client.mac()).await? {
         debug!("Device {} authorized as node #{id}", client.mac());
         client.set_id(id);
-        client.send_response(Response::Ok)?;
     } else {
         warn!("Device {} is not authorized", client.mac());
-        client.send_response(Response::Reject)?;
+        client.send_response(Response::Reject).await?;
         return Ok(());
     }
 
-    let mut last_submit = None;
+    let id = client.id();
+
+    // Only nodes that greet with `Resume` know about sessions, others expect a plain `Ok`.
+    let resumable = client.resume_token();
+    let resumed = resumable
+        .filter(|token| *token != NEW_SESSION)
+        .and_then(|token| sessions.resume(token, id));
+
+    let (lease, state, resumed) = match resumed {
+        Some((lease, state)) => {
+            debug!("{id}: Resumed session");
+            (lease, state, true)
+        }
+        None => (sessions.open(id), SessionState::default(), false),
+    };
+
+    let response = if resumable.is_some() {
+        Response::Welcome {
+            session: lease.token(),
+            resumed,
+        }
+    } else {
+        Response::Ok
+    };
+
+    if let Err(why) = client.send_response(response).await {
+        sessions.close(lease);
+        return Err(why);
+    }
+
+    let result = serve_session(&mut client, db, sessions, lease, state).await;
+
+    // The node did not get a response if storing it's data failed, so it may retry as well.
+    if resumable.is_some()
+        && matches!(
+            result,
+            Err(Error::Io(_) | Error::Quit | Error::Timeout | Error::Storage(_))
+        )
+    {
+        debug!("{id}: Connection lost, session can be resumed");
+        sessions.suspend(lease);
+    } else {
+        sessions.close(lease);
+    }
+
+    result
+}
+
+async fn serve_session(
+    client: &mut Client,
+    db: &dyn Storage,
+    sessions: &SessionStore,
+    lease: Lease,
+    mut state: SessionState,
+) -> Result<(), Error> {
+    let id = client.id();
+    let mut rate_limiter = new_rate_limiter(CONFIG.rate_limits.max_requests);
+    let mut queued_rate_limiter = new_rate_limiter(CONFIG.rate_limits.max_queued);
+    let mut relay_rate_limiters = HashMap::new();
+    let mut replay = state.replay.take();
 
     loop {
-        let request = client.await_request()?;
+        let request = client.await_request().await?;
+
+        let limiter = match &request {
+            RequestRef::Relay { child, .. } => relay_rate_limiters
+                .entry(child.clone())
+                .or_insert_with(|| new_rate_limiter(CONFIG.rate_limits.max_requests)),
+            // Nodes post their whole backlog at once, which shouldn't end the session.
+            RequestRef::PostQueued { .. } => &mut queued_rate_limiter,
+            _ => &mut rate_limiter,
+        };
 
-        if rate_limiter.hit() {
-            error!("{}: Exceeded request limits", client.id());
+        if limiter.hit() {
+            error!("{id}: Exceeded request limits");
             break;
         }
 
-        if request == Request::Bye {
-            debug!("{}: Bye", client.id());
+        if request == RequestRef::Bye {
+            debug!("{id}: Bye");
+            client.shutdown().await;
             break;
         }
 
-        let response =
-            handle_request(request, &client, db, &mut last_submit).ok_or(Error::BadRequest)?;
+        if let Some((last_request, last_response)) = replay.take() {
+            if request.clone().into_owned() == last_request {
+                debug!("{id}: Request was retried, replaying previous response");
+                client.send_response(last_response).await?;
+                continue;
+            }
+        }
+
+        let stateful = is_stateful(&request).then(|| request.clone().into_owned());
+
+        let response = match request {
+            RequestRef::Relay { child, request } => {
+                handle_relay(&child, *request, id, db, &mut state.relayed).await
+            }
+            request => handle_request(request, id, db, &mut state.last_submit).await,
+        }?;
+
+        if let Some(request) = stateful {
+            state.replay = Some((request, response.clone()));
+        }
 
-        client.send_response(response)?;
+        sessions.record(lease, &state);
+        client.send_response(response).await?;
     }
 
     Ok(())
 }
 
-fn handle_request(
-    req: Request,
-    client: &Client,
-    db: &DatabaseClient,
+async fn handle_relay(
+    child: &Mac,
+    req: RequestRef<'_>,
+    gateway: NodeId,
+    db: &dyn Storage,
+    relayed: &mut HashMap<NodeId, MeasurementId>,
+) -> Result<Response, Error> {
+    if matches!(
+        req,
+        RequestRef::Ping
+            | RequestRef::Bye
+            | RequestRef::Hello { .. }
+            | RequestRef::Resume { .. }
+            | RequestRef::Relay { .. }
+    ) {
+        error!("{gateway}: Attempted to relay {req:?}, which is not allowed");
+        return Err(Error::BadRequest);
+    }
+
+    let Some(child_id) = db.authorize_relayed_device(child, gateway).await? else {
+        warn!("{gateway}: Device {child} is not assigned to this gateway");
+        return Ok(Response::Reject);
+    };
+
+    debug!("{gateway}: Relaying for node #{child_id}");
+
+    // Gateways forward every reading of their nodes in a single session,
+    // so each new measurement replaces the previous one.
+    let mut last_submit = if matches!(req, RequestRef::PostResults { .. }) {
+        None
+    } else {
+        relayed.get(&child_id).copied()
+    };
+
+    let response = handle_request(req, child_id, db, &mut last_submit).await?;
+
+    if let Some(measurement) = last_submit {
+        relayed.insert(child_id, measurement);
+    }
+
+    Ok(response)
+}
+
+fn is_stateful(req: &RequestRef<'_>) -> bool {
+    match req {
+        RequestRef::PostResults { .. }
+        | RequestRef::PostQueued { .. }
+        | RequestRef::PostStats { .. }
+        | RequestRef::PostLocation { .. }
+        | RequestRef::SendNotification { .. } => true,
+        RequestRef::Relay { request, .. } => is_stateful(request),
+        _ => false,
+    }
+}
+
+fn new_rate_limiter(max_requests: usize) -> RateLimiter {
+    RateLimiter::new(
+        Duration::from_secs(CONFIG.rate_limits.time_frame),
+        max_requests,
+    )
+}
+
+async fn handle_request(
+    req: RequestRef<'_>,
+    id: NodeId,
+    db: &dyn Storage,
     last_submit: &mut Option<MeasurementId>,
-) -> Option<Response> {
-    debug!(
-        "Handling {req:#?} ({} bytes)",
-        Message::Request(req.clone()).size()
-    );
+) -> Result<Response, Error> {
+    debug!("Handling {req:#?}");
 
     match req {
-        Request::Ping => Some(Response::Pong),
-        Request::Hello { .. } => {
+        RequestRef::Ping => Ok(Response::Pong),
+        RequestRef::Hello { .. } | RequestRef::Resume { .. } => {
             warn!("Received double `Hello` messages");
-            None
+            Err(Error::BadRequest)
         }
-        Request::PostResults {
+        RequestRef::PostResults {
             temperature,
             humidity,
             air_pressure,
         } => {
             if last_submit.is_some() {
-                error!(
-                    "{}: Submitted multiple posts, which is not allowed",
-                    client.id()
-                );
-                return None;
+                error!("{id}: Submitted multiple posts, which is not allowed");
+                return Err(Error::BadRequest);
             }
 
-            debug!(
-                "{}: {temperature}C, {humidity}%, {air_pressure:?}hPa",
-                client.id()
+            debug!("{id}: {temperature}C, {humidity}%, {air_pressure:?}hPa");
+            *last_submit = Some(
+                db.post_results(id, temperature, humidity, air_pressure)
+                    .await?,
             );
-            *last_submit = Some(db.post_results(client.id(), temperature, humidity, air_pressure));
-            Some(Response::Ok)
+            Ok(Response::Ok)
         }
-        Request::PostStats {
-            ref battery,
+        RequestRef::PostStats {
+            battery,
             wifi_ssid,
             wifi_rssi,
         } => {
             let Some(last_measurement_id) = last_submit else {
-                error!("{}: Missing measurement", client.id());
-                return None;
+                error!("{id}: Missing measurement");
+                return Err(Error::BadRequest);
+            };
+
+            db.post_stats(*last_measurement_id, &battery, wifi_ssid, wifi_rssi)
+                .await?;
+            Ok(Response::Ok)
+        }
+        RequestRef::PostLocation {
+            latitude,
+            longitude,
+            altitude,
+        } => {
+            let (Ok(lat), Ok(lon)) = (f64::try_from(latitude), f64::try_from(longitude)) else {
+                error!("{id}: Unrepresentable location");
+                return Err(Error::BadRequest);
+            };
+
+            if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+                error!("{id}: Location {lat}, {lon} is out of range");
+                return Err(Error::BadRequest);
+            }
+
+            debug!("{id}: Located at {lat}, {lon} ({altitude:?}m)");
+            db.set_location(id, lat, lon, altitude).await?;
+
+            Ok(Response::Ok)
+        }
+        RequestRef::PostQueued {
+            measured_at,
+            temperature,
+            humidity,
+            air_pressure,
+            battery,
+            wifi_ssid,
+            wifi_rssi,
+        } => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+
+            // Rejected measurements are dropped by the node, postponed ones are kept,
+            // for eg. until it's clock is corrected.
+            let Ok(timestamp) = i64::try_from(measured_at) else {
+                error!("{id}: Invalid measurement time {measured_at}");
+                return Ok(Response::Reject);
+            };
+
+            if measured_at > now.saturating_add(MAX_CLOCK_SKEW) {
+                error!("{id}: Queued measurement is from the future ({measured_at})");
+                return Ok(Response::Postponed);
+            }
+
+            debug!(
+                "{id}: Queued at {measured_at}: {temperature}C, {humidity}%, {air_pressure:?}hPa"
+            );
+            let measurement = match db
+                .post_queued_results(id, timestamp, temperature, humidity, air_pressure)
+                .await
+            {
+                Ok(Some(measurement)) => measurement,
+                // The node did not get the response to the previous attempt.
+                Ok(None) => {
+                    debug!("{id}: Measurement from {measured_at} is already stored");
+                    return Ok(Response::Ok);
+                }
+                Err(StorageError::Constraint(why)) => {
+                    error!("{id}: Failed to store queued measurement: {why}");
+                    return Ok(Response::Reject);
+                }
+                Err(why) => return Err(why.into()),
             };
 
-            db.post_stats(*last_measurement_id, battery, &wifi_ssid, wifi_rssi);
-            Some(Response::Ok)
+            if let (Some(battery), Some(wifi_ssid), Some(wifi_rssi)) =
+                (battery, wifi_ssid, wifi_rssi)
+            {
+                db.post_stats(measurement, &battery, wifi_ssid, wifi_rssi)
+                    .await?;
+            }
+
+            Ok(Response::Ok)
         }
-        Request::SendNotification(message) => {
-            db.create_notification(client.id(), &message);
-            Some(Response::Ok)
+        RequestRef::SendNotification {
+            content,
+            severity,
+            category,
+        } => {
+            if content.len() > MAX_NOTIFICATION_LEN {
+                error!("{id}: Notification content is too long");
+                return Err(Error::BadRequest);
+            }
+
+            if db.notifications_muted(id).await? {
+                debug!(
+                    "{id}: Notifications are muted, dropping {severity:?} {category:?} notification"
+                );
+                return Ok(Response::Ok);
+            }
+
+            db.create_notification(
+                id,
+                content,
+                severity,
+                category,
+                CONFIG.notifications.expiry(),
+                CONFIG.notifications.deduplicate,
+            )
+            .await?;
+            Ok(Response::Ok)
         }
-        Request::GetSettings(settings) => {
-            let values = db.get_settings(client.id(), &settings);
+        RequestRef::GetSettings(settings) => {
+            let settings: Vec<SettingName> = settings.iter().collect();
+            let values = db.get_settings(id, &settings).await?;
             let mut results = Vec::with_capacity(values.len());
 
             for (i, value) in values.into_iter().enumerate() {
                 let setting = settings[i];
 
-                let result = value.map_or_else(
-                    || {
-                        let default = settings[i].default_value();
-                        warn!(
-                            "{}: {setting:?} is not set, returning default {default:?}",
-                            client.id()
-                        );
-                        settings[i].default_value()
-                    },
-                    |value| value,
-                );
+                let result = value.unwrap_or_else(|| {
+                    let default = settings[i].default_value();
+                    warn!("{id}: {setting:?} is not set, returning default {default:?}");
+                    settings[i].default_value()
+                });
 
                 results.push(result);
             }
 
-            Some(Response::Settings(results.into_boxed_slice()))
+            Ok(Response::Settings(results.into_boxed_slice()))
         }
-        Request::Bye => unreachable!(),
+        RequestRef::Relay { .. } | RequestRef::Bye => unreachable!(),
     }
 }
 
-fn set_panic_hook(connection_count: Arc<AtomicU32>) {
-    let default = panic::take_hook();
+#[cfg(test)]
+mod tests {
+    use super::{handle_client, handle_request};
+    use crate::{
+        error::Error,
+        server::{
+            config::Config,
+            session::SessionStore,
+            storage::{NodeLocation, Storage, StorageError, StorageResult, StoredNotification},
+            tls::Stream,
+        },
+        CONFIG,
+    };
+    use always_cell::AlwaysCell;
+    use async_trait::async_trait;
+    use pwmp_client::{
+        outbox::{DropPolicy, MemoryStorage, Outbox, QueuedMeasurement},
+        retry::RetryPolicy,
+        PwmpClient,
+    };
+    use pwmp_types::{
+        aliases::{
+            AirPressure, Altitude, BatteryVoltage, Humidity, MeasurementId, Rssi, Temperature,
+        },
+        borrowed::MessageRef,
+        dec,
+        mac::Mac,
+        multitype::SettingValue,
+        notification::{NotificationCategory, NotificationSeverity},
+        request::Request,
+        response::Response,
+        setting::SettingName,
+        Decimal, Message, NodeId,
+    };
+    use std::{
+        net::Ipv4Addr,
+        sync::Mutex,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+    use tokio::net::TcpListener;
+
+    const NODE: NodeId = 1;
+
+    /// Node, time if the measurement was queued, temperature and humidity.
+    type StoredMeasurement = (NodeId, Option<i64>, Temperature, Humidity);
+
+    #[derive(Default)]
+    struct TestStorage {
+        measurements: Mutex<Vec<StoredMeasurement>>,
+        stats: Mutex<Vec<(MeasurementId, Box<str>)>>,
+        notifications: Mutex<Vec<Box<str>>>,
+        settings: Vec<(SettingName, SettingValue)>,
+        failure: Option<fn() -> StorageError>,
+    }
+
+    impl TestStorage {
+        fn check(&self) -> StorageResult<()> {
+            self.failure.map_or(Ok(()), |failure| Err(failure()))
+        }
+
+        fn store_measurement(
+            &self,
+            node: NodeId,
+            measured_at: Option<i64>,
+            temp: Temperature,
+            hum: Humidity,
+        ) -> StorageResult<MeasurementId> {
+            self.check()?;
+            let mut measurements = self.measurements.lock().unwrap();
+            measurements.push((node, measured_at, temp, hum));
+
+            Ok(measurements.len() as MeasurementId)
+        }
+    }
+
+    #[async_trait]
+    impl Storage for TestStorage {
+        async fn authorize_device(&self, _mac: &Mac) -> StorageResult<Option<NodeId>> {
+            Ok(Some(NODE))
+        }
+
+        async fn authorize_relayed_device(
+            &self,
+            _mac: &Mac,
+            _gateway: NodeId,
+        ) -> StorageResult<Option<NodeId>> {
+            Ok(None)
+        }
+
+        async fn post_results(
+            &self,
+            node: NodeId,
+            temp: Temperature,
+            hum: Humidity,
+            _air_p: Option<AirPressure>,
+        ) -> StorageResult<MeasurementId> {
+            self.store_measurement(node, None, temp, hum)
+        }
+
+        async fn post_queued_results(
+            &self,
+            node: NodeId,
+            measured_at: i64,
+            temp: Temperature,
+            hum: Humidity,
+            _air_p: Option<AirPressure>,
+        ) -> StorageResult<Option<MeasurementId>> {
+            if self
+                .measurements
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|stored| stored.0 == node && stored.1 == Some(measured_at))
+            {
+                return Ok(None);
+            }
+
+            self.store_measurement(node, Some(measured_at), temp, hum)
+                .map(Some)
+        }
+
+        async fn post_stats(
+            &self,
+            measurement: MeasurementId,
+            _battery: &BatteryVoltage,
+            wifi_ssid: &str,
+            _wifi_rssi: Rssi,
+        ) -> StorageResult<()> {
+            self.check()?;
+            self.stats
+                .lock()
+                .unwrap()
+                .push((measurement, wifi_ssid.into()));
+
+            Ok(())
+        }
+
+        async fn set_location(
+            &self,
+            _id: NodeId,
+            _latitude: f64,
+            _longitude: f64,
+            _altitude: Option<Altitude>,
+        ) -> StorageResult<()> {
+            self.check()
+        }
+
+        async fn nodes_within_radius(
+            &self,
+            _latitude: f64,
+            _longitude: f64,
+            _radius_km: f64,
+        ) -> StorageResult<Vec<NodeLocation>> {
+            Ok(Vec::new())
+        }
+
+        async fn nodes_within_box(
+            &self,
+            _min: (f64, f64),
+            _max: (f64, f64),
+        ) -> StorageResult<Vec<NodeLocation>> {
+            Ok(Vec::new())
+        }
+
+        async fn create_notification(
+            &self,
+            _id: NodeId,
+            content: &str,
+            _severity: NotificationSeverity,
+            _category: NotificationCategory,
+            _expiry: Option<i32>,
+            _deduplicate: bool,
+        ) -> StorageResult<()> {
+            self.check()?;
+            self.notifications.lock().unwrap().push(content.into());
+
+            Ok(())
+        }
+
+        async fn list_notifications(
+            &self,
+            _unread_only: bool,
+        ) -> StorageResult<Vec<StoredNotification>> {
+            Ok(Vec::new())
+        }
+
+        async fn mark_notification_read(&self, _id: i32) -> StorageResult<bool> {
+            Ok(false)
+        }
+
+        async fn purge_notifications(&self) -> StorageResult<u64> {
+            Ok(0)
+        }
+
+        async fn get_settings(
+            &self,
+            _id: NodeId,
+            settings: &[SettingName],
+        ) -> StorageResult<Vec<Option<SettingValue>>> {
+            self.check()?;
+
+            Ok(settings
+                .iter()
+                .map(|setting| {
+                    self.settings
+                        .iter()
+                        .find(|(name, _)| name == setting)
+                        .map(|(_, value)| value.clone())
+                })
+                .collect())
+        }
+    }
+
+    async fn handle(
+        db: &TestStorage,
+        req: Request,
+        last_submit: &mut Option<MeasurementId>,
+    ) -> Result<Response, Error> {
+        let _ = AlwaysCell::try_set(&CONFIG, Config::default());
+        let raw = Message::Request(req).to_raw();
+        let req = MessageRef::from_raw(&raw).unwrap().to_request().unwrap();
+
+        handle_request(req, NODE, db, last_submit).await
+    }
+
+    fn measurement() -> Request {
+        Request::PostResults {
+            temperature: dec!(21.50),
+            humidity: 45,
+            air_pressure: None,
+        }
+    }
+
+    fn queued(measured_at: u64) -> Request {
+        Request::PostQueued {
+            measured_at,
+            temperature: dec!(12.25),
+            humidity: 80,
+            air_pressure: None,
+            battery: Some(dec!(3.81)),
+            wifi_ssid: Some("Weather".into()),
+            wifi_rssi: Some(-67),
+        }
+    }
+
+    fn notification(content: &str) -> Request {
+        Request::SendNotification {
+            content: content.into(),
+            severity: NotificationSeverity::Info,
+            category: NotificationCategory::General,
+        }
+    }
+
+    #[tokio::test]
+    async fn ping() {
+        let db = TestStorage::default();
+
+        let response = handle(&db, Request::Ping, &mut None).await;
+
+        assert!(matches!(response, Ok(Response::Pong)));
+    }
+
+    #[tokio::test]
+    async fn post_results_and_stats() {
+        let db = TestStorage::default();
+        let mut last_submit = None;
+
+        let response = handle(&db, measurement(), &mut last_submit).await;
+        assert!(matches!(response, Ok(Response::Ok)));
+        assert_eq!(last_submit, Some(1));
+
+        let stats = Request::PostStats {
+            battery: dec!(3.81),
+            wifi_ssid: "Weather".into(),
+            wifi_rssi: -67,
+        };
+        let response = handle(&db, stats, &mut last_submit).await;
+        assert!(matches!(response, Ok(Response::Ok)));
+
+        assert_eq!(*db.stats.lock().unwrap(), [(1, "Weather".into())]);
+    }
+
+    #[tokio::test]
+    async fn multiple_posts() {
+        let db = TestStorage::default();
+        let mut last_submit = None;
 
-    panic::set_hook(Box::new(move |panic_info| {
-        warn!("A client thread has paniced");
-        default(panic_info);
-        connection_count.fetch_sub(1, Ordering::Relaxed);
-    }));
+        handle(&db, measurement(), &mut last_submit).await.unwrap();
+        let response = handle(&db, measurement(), &mut last_submit).await;
+
+        assert!(matches!(response, Err(Error::BadRequest)));
+        assert_eq!(db.measurements.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn stats_without_measurement() {
+        let db = TestStorage::default();
+        let stats = Request::PostStats {
+            battery: dec!(3.81),
+            wifi_ssid: "Weather".into(),
+            wifi_rssi: -67,
+        };
+
+        let response = handle(&db, stats, &mut None).await;
+
+        assert!(matches!(response, Err(Error::BadRequest)));
+        assert!(db.stats.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn storage_failure() {
+        let db = TestStorage {
+            failure: Some(|| StorageError::Database(sqlx::Error::PoolTimedOut)),
+            ..Default::default()
+        };
+        let mut last_submit = None;
+
+        let response = handle(&db, measurement(), &mut last_submit).await;
+
+        assert!(matches!(
+            response,
+            Err(Error::Storage(StorageError::Database(_)))
+        ));
+        assert_eq!(last_submit, None);
+    }
+
+    #[tokio::test]
+    async fn queued_measurement() {
+        let db = TestStorage::default();
+
+        let response = handle(&db, queued(1_700_000_000), &mut None).await;
+
+        assert!(matches!(response, Ok(Response::Ok)));
+        assert_eq!(
+            *db.measurements.lock().unwrap(),
+            [(NODE, Some(1_700_000_000), dec!(12.25), 80)]
+        );
+        assert_eq!(db.stats.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_measurement_rejected() {
+        let db = TestStorage {
+            failure: Some(|| StorageError::Constraint("humidity is out of range".into())),
+            ..Default::default()
+        };
+
+        let future = handle(&db, queued(u64::MAX / 2), &mut None).await;
+        let invalid = handle(&db, queued(1_700_000_000), &mut None).await;
+
+        assert!(matches!(future, Ok(Response::Postponed)));
+        assert!(matches!(invalid, Ok(Response::Reject)));
+    }
+
+    #[tokio::test]
+    async fn queued_measurement_clock_skew() {
+        let db = TestStorage::default();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let skewed = handle(&db, queued(now + 30), &mut None).await;
+        let future = handle(&db, queued(now + 3600), &mut None).await;
+
+        assert!(matches!(skewed, Ok(Response::Ok)));
+        assert!(matches!(future, Ok(Response::Postponed)));
+        assert_eq!(db.measurements.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_measurement_duplicate() {
+        let db = TestStorage::default();
+
+        let first = handle(&db, queued(1_700_000_000), &mut None).await;
+        let duplicate = handle(&db, queued(1_700_000_000), &mut None).await;
+
+        assert!(matches!(first, Ok(Response::Ok)));
+        assert!(matches!(duplicate, Ok(Response::Ok)));
+        assert_eq!(db.measurements.lock().unwrap().len(), 1);
+        assert_eq!(db.stats.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_backlog() {
+        let _ = AlwaysCell::try_set(&CONFIG, Config::default());
+        let backlog = CONFIG.rate_limits.max_requests + 2;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Without retries, the node would fail if the session was closed by the rate limiter.
+        let node = tokio::task::spawn_blocking(move || {
+            let mut outbox =
+                Outbox::new(MemoryStorage::default(), backlog, DropPolicy::DropOldest).unwrap();
+            for i in 0..backlog {
+                outbox
+                    .push(QueuedMeasurement {
+                        measured_at: 1_700_000_000 + i as u64,
+                        temperature: dec!(12.25),
+                        humidity: 80,
+                        air_pressure: None,
+                        stats: None,
+                    })
+                    .unwrap();
+            }
+
+            let mut client = PwmpClient::builder(Mac::new(1, 2, 3, 4, 5, 6))
+                .retry(RetryPolicy::never())
+                .build(addr)
+                .unwrap();
+            let posted = client.attach_outbox(outbox);
+            client.close().unwrap();
+
+            posted
+        });
+
+        let db = TestStorage::default();
+        let sessions = SessionStore::new(Duration::from_secs(30));
+        let (socket, _) = listener.accept().await.unwrap();
+        handle_client(Stream::Plain(socket), &db, &sessions)
+            .await
+            .unwrap();
+
+        assert_eq!(node.await.unwrap().unwrap(), backlog);
+        assert_eq!(db.measurements.lock().unwrap().len(), backlog);
+    }
+
+    #[tokio::test]
+    async fn notifications() {
+        let db = TestStorage::default();
+
+        let response = handle(&db, notification("Battery low"), &mut None).await;
+        let too_long = handle(&db, notification(&"x".repeat(65)), &mut None).await;
+
+        assert!(matches!(response, Ok(Response::Ok)));
+        assert!(matches!(too_long, Err(Error::BadRequest)));
+        assert_eq!(*db.notifications.lock().unwrap(), ["Battery low".into()]);
+    }
+
+    #[tokio::test]
+    async fn muted_notifications() {
+        let db = TestStorage {
+            settings: vec![(SettingName::MuteNotifications, SettingValue::Boolean(true))],
+            ..Default::default()
+        };
+
+        let response = handle(&db, notification("Battery low"), &mut None).await;
+
+        assert!(matches!(response, Ok(Response::Ok)));
+        assert!(db.notifications.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn settings_with_defaults() {
+        let db = TestStorage {
+            settings: vec![(SettingName::SleepTime, SettingValue::Number(300))],
+            ..Default::default()
+        };
+        let request = Request::GetSettings([SettingName::SleepTime, SettingName::Ota].into());
+
+        let response = handle(&db, request, &mut None).await;
+
+        let Ok(Response::Settings(values)) = response else {
+            panic!("Expected settings, got {response:?}");
+        };
+        assert_eq!(
+            *values,
+            [SettingValue::Number(300), SettingName::Ota.default_value()]
+        );
+    }
+
+    #[tokio::test]
+    async fn location_out_of_range() {
+        let db = TestStorage::default();
+        let request = Request::PostLocation {
+            latitude: dec!(91.0),
+            longitude: dec!(14.5),
+            altitude: None,
+        };
+
+        let response = handle(&db, request, &mut None).await;
+
+        assert!(matches!(response, Err(Error::BadRequest)));
+    }
 }
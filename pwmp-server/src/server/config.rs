@@ -1,7 +1,9 @@
+use pwmp_types::discovery::DISCOVERY_PORT;
 use serde::{Deserialize, Serialize};
 use std::{
     net::{Ipv4Addr, SocketAddrV4},
     path::PathBuf,
+    time::Duration,
 };
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -11,22 +13,59 @@ pub struct Config {
     pub limits: LimitsConfig,
     #[serde(rename = "rate_limiter")]
     pub rate_limits: RateLimitConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub host: Ipv4Addr,
     pub port: u16,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DatabaseConfig {
+    pub backend: DatabaseBackend,
     pub host: Box<str>,
     pub port: u16,
     pub user: Box<str>,
     pub password: Box<str>,
     pub name: Box<str>,
     pub ssl: bool,
+    /// Database file used by the SQLite backend. Created if it doesn't exist.
+    pub path: PathBuf,
+    /// Devices and settings loaded by the memory backend. Demo devices are used if not set.
+    pub seed: Option<PathBuf>,
+    /// File the memory backend periodically saves it's data to, and restores it from on startup.
+    pub snapshot: Option<PathBuf>,
+    /// Seconds between snapshots.
+    pub snapshot_interval: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[default]
+    Postgres,
+    Sqlite,
+    Memory,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,24 +78,68 @@ pub struct LimitsConfig {
 pub struct RateLimitConfig {
     pub time_frame: u64,
     pub max_requests: usize,
+    /// Queued measurements a node may post per time frame, on top of `max_requests`.
+    #[serde(default = "RateLimitConfig::default_max_queued")]
+    pub max_queued: usize,
+    /// Connections per peer address and time frame.
     pub max_connections: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    pub expiry: u32,
+    pub deduplicate: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsConfig {
+    pub resume_window: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscoveryConfig {
+    pub enabled: bool,
+    pub port: u16,
+    /// Host name announced to clients, instead of the address the announcement is sent from.
+    #[serde(default)]
+    pub host: Option<Box<str>>,
+}
+
+/// Seconds to wait for a node before closing the connection.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutsConfig {
+    /// Completing the TLS handshake.
+    pub handshake: u64,
+    /// Receiving the greeting, after the connection was set up.
+    pub hello: u64,
+    /// Receiving the next request of a session.
+    pub idle: u64,
+}
+
 impl Default for RateLimitConfig {
     fn default() -> Self {
         Self {
             time_frame: 1,
             max_requests: 4,
+            max_queued: Self::default_max_queued(),
             max_connections: 4,
         }
     }
 }
 
+impl RateLimitConfig {
+    const fn default_max_queued() -> usize {
+        32
+    }
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             host: Ipv4Addr::new(0, 0, 0, 0),
             port: 55300,
+            tls: None,
         }
     }
 }
@@ -64,12 +147,17 @@ impl Default for ServerConfig {
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
+            backend: DatabaseBackend::default(),
             host: "192.168.0.12".into(),
             port: 5432,
             user: "root".into(),
             password: "root".into(),
             name: "pixelweather".into(),
             ssl: false,
+            path: Config::default_path().with_file_name("pixelweather.db"),
+            seed: None,
+            snapshot: None,
+            snapshot_interval: 60,
         }
     }
 }
@@ -77,12 +165,67 @@ impl Default for DatabaseConfig {
 impl Default for LimitsConfig {
     fn default() -> Self {
         Self {
-            max_devices: 10,
+            max_devices: 100,
             max_settings: 10,
         }
     }
 }
 
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            expiry: 7 * 24 * 60 * 60,
+            deduplicate: true,
+        }
+    }
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self { resume_window: 30 }
+    }
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DISCOVERY_PORT,
+            host: None,
+        }
+    }
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            handshake: 10,
+            hello: 10,
+            idle: 30,
+        }
+    }
+}
+
+impl TimeoutsConfig {
+    pub const fn handshake(&self) -> Duration {
+        Duration::from_secs(self.handshake)
+    }
+
+    pub const fn hello(&self) -> Duration {
+        Duration::from_secs(self.hello)
+    }
+
+    pub const fn idle(&self) -> Duration {
+        Duration::from_secs(self.idle)
+    }
+}
+
+impl NotificationsConfig {
+    pub fn expiry(&self) -> Option<i32> {
+        (self.expiry != 0).then(|| self.expiry.min(i32::MAX as u32) as i32)
+    }
+}
+
 impl Config {
     pub fn default_path() -> PathBuf {
         homedir::my_home()
@@ -0,0 +1,50 @@
+use crate::server::config::Config;
+use log::{debug, error, warn};
+use pwmp_types::discovery::{Discovery, DISCOVERY_GROUP, PROTOCOL_VERSION};
+use std::{io, net::Ipv4Addr};
+use tokio::net::UdpSocket;
+
+/// Start answering discovery probes in the background.
+pub async fn spawn_responder(config: &Config) -> io::Result<()> {
+    // Sockets bound to a unicast address don't receive broadcast and multicast probes, so the
+    // configured address only selects the interface used to join the multicast group.
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, config.discovery.port)).await?;
+
+    // Broadcast probes are received anyway, so the responder still works without multicast.
+    if let Err(why) = socket.join_multicast_v4(DISCOVERY_GROUP, config.server.host) {
+        warn!("Failed to join discovery multicast group {DISCOVERY_GROUP}: {why}");
+    }
+
+    let announce = Discovery::Announce {
+        host: config.discovery.host.clone(),
+        port: config.server.port,
+        version: PROTOCOL_VERSION,
+        tls: config.server.tls.is_some(),
+    }
+    .to_raw();
+
+    tokio::spawn(async move {
+        let mut buf = [0; 64];
+
+        loop {
+            let (read, from) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(why) => {
+                    error!("Discovery responder failed: {why}");
+                    return;
+                }
+            };
+
+            if Discovery::from_raw(&buf[..read]) != Some(Discovery::Probe) {
+                continue;
+            }
+
+            debug!("{from}: Answering discovery probe");
+            if let Err(why) = socket.send_to(&announce, from).await {
+                warn!("{from}: Failed to answer discovery probe: {why}");
+            }
+        }
+    });
+
+    Ok(())
+}
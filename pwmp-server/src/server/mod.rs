@@ -1,33 +1,97 @@
 use crate::{
-    server::{db::DatabaseClient, server_handle::server_loop},
+    server::{
+        config::DatabaseBackend,
+        discovery::spawn_responder,
+        server_handle::server_loop,
+        storage::{memory::MemoryClient, postgres::DatabaseClient, sqlite::SqliteClient, Storage},
+        tls::load_config,
+    },
     CONFIG,
 };
 use log::{error, info};
-use std::{net::TcpListener, process::exit};
+use std::process::exit;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
 
 mod client;
 mod client_handle;
 pub mod config;
-pub mod db;
+pub mod discovery;
 pub mod rate_limit;
 pub mod server_handle;
+pub mod session;
+pub mod storage;
+pub mod tls;
 
-pub fn server_main() {
-    info!("Connecting to database at {}", CONFIG.database.host);
-    let db = match DatabaseClient::new(&CONFIG) {
-        Ok(db) => db,
-        Err(why) => {
-            error!("Failed to connect to database: {why}");
-            exit(1);
-        }
-    };
+pub async fn server_main() {
+    let db = connect_storage().await;
 
-    let Ok(server) = TcpListener::bind(CONFIG.server_bind_addr()) else {
+    match db.purge_notifications().await {
+        Ok(0) => (),
+        Ok(purged) => info!("Purged {purged} expired notifications"),
+        Err(why) => error!("Failed to purge expired notifications: {why}"),
+    }
+
+    let tls = CONFIG
+        .server
+        .tls
+        .as_ref()
+        .map(|tls| match load_config(tls) {
+            Ok(config) => {
+                info!("TLS enabled using {}", tls.cert.display());
+                TlsAcceptor::from(config)
+            }
+            Err(why) => {
+                error!("Failed to load TLS configuration: {why}");
+                exit(1);
+            }
+        });
+
+    let Ok(server) = TcpListener::bind(CONFIG.server_bind_addr()).await else {
         eprintln!("Failed to bind to {}", CONFIG.server_bind_addr());
         exit(1);
     };
 
     info!("Server started on {}", CONFIG.server_bind_addr());
 
-    server_loop(&server, db);
+    if CONFIG.discovery.enabled {
+        match spawn_responder(&CONFIG).await {
+            Ok(()) => info!(
+                "Answering discovery probes on port {}",
+                CONFIG.discovery.port
+            ),
+            Err(why) => error!("Failed to start discovery responder: {why}"),
+        }
+    }
+
+    server_loop(server, db, tls).await;
+}
+
+pub async fn connect_storage() -> Box<dyn Storage> {
+    let result = match CONFIG.database.backend {
+        DatabaseBackend::Postgres => {
+            info!("Connecting to database at {}", CONFIG.database.host);
+            DatabaseClient::new(&CONFIG)
+                .await
+                .map(|db| Box::new(db) as Box<dyn Storage>)
+        }
+        DatabaseBackend::Sqlite => {
+            info!("Opening database {}", CONFIG.database.path.display());
+            SqliteClient::new(&CONFIG)
+                .await
+                .map(|db| Box::new(db) as Box<dyn Storage>)
+        }
+        DatabaseBackend::Memory => {
+            info!("Using in-memory storage");
+            MemoryClient::new(&CONFIG).map(|db| Box::new(db) as Box<dyn Storage>)
+        }
+    };
+
+    match result {
+        Ok(db) => db,
+        Err(why) => {
+            error!("Failed to connect to database: {why}");
+            exit(1);
+        }
+    }
 }
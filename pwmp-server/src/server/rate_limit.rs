@@ -17,8 +17,13 @@ impl RateLimiter {
         }
     }
 
+    /// Returns whether the time frame of the last hits has passed.
+    pub fn expired(&self) -> bool {
+        self.time.elapsed() >= self.time_frame
+    }
+
     pub fn hit(&mut self) -> bool {
-        if self.time.elapsed() >= self.time_frame {
+        if self.expired() {
             self.hits = 0;
             self.time = Instant::now();
         }
@@ -1,67 +1,78 @@
-use super::{db::DatabaseClient, rate_limit::RateLimiter};
+use super::{rate_limit::RateLimiter, session::SessionStore, storage::Storage, tls::Stream};
 use crate::{server::client_handle::handle_client, CONFIG};
 use log::{debug, error, warn};
-use std::{
-    net::TcpListener,
-    sync::{
-        atomic::{AtomicU32, Ordering},
-        Arc,
-    },
-    thread,
-    time::Duration,
-};
+use std::{collections::HashMap, net::IpAddr, sync::Arc, time::Duration};
+use tokio::{net::TcpListener, sync::Semaphore, time::timeout};
+use tokio_rustls::TlsAcceptor;
 
-pub fn server_loop(server: &TcpListener, db: DatabaseClient) {
-    let connections = Arc::new(AtomicU32::new(0));
-    let shared_db = Arc::new(db);
-    let mut rate_limiter = RateLimiter::new(
-        Duration::from_secs(CONFIG.rate_limits.time_frame),
-        CONFIG.rate_limits.max_connections,
-    );
+pub async fn server_loop(server: TcpListener, db: Box<dyn Storage>, tls: Option<TlsAcceptor>) {
+    let connections = Arc::new(Semaphore::new(CONFIG.limits.max_devices as usize));
+    let shared_db: Arc<dyn Storage> = Arc::from(db);
+    let sessions = Arc::new(SessionStore::new(Duration::from_secs(
+        CONFIG.sessions.resume_window,
+    )));
+    // Connections are limited per address, so nodes that wake up together aren't turned away.
+    let mut rate_limiters: HashMap<IpAddr, RateLimiter> = HashMap::new();
 
-    for client in server.incoming() {
-        if connections.load(Ordering::Relaxed) == CONFIG.limits.max_devices {
-            warn!("Maximum number of connections reached, ignoring connection");
+    loop {
+        let Ok((client, peer_addr)) = server.accept().await else {
+            warn!("A client failed to connect");
             continue;
-        }
+        };
+
+        // Addresses that didn't connect within the time frame don't need a limiter anymore.
+        rate_limiters.retain(|_, limiter| !limiter.expired());
+        let rate_limiter = rate_limiters.entry(peer_addr.ip()).or_insert_with(|| {
+            RateLimiter::new(
+                Duration::from_secs(CONFIG.rate_limits.time_frame),
+                CONFIG.rate_limits.max_connections,
+            )
+        });
 
         if rate_limiter.hit() {
-            warn!("Rate limiting");
+            warn!("{peer_addr}: Rate limiting");
             continue;
         }
 
-        let Ok(client) = client else {
-            warn!("A client failed to connect");
-            continue;
-        };
-        let Ok(peer_addr) = client.peer_addr() else {
-            error!("Failed to get a clients peer address information");
+        let Ok(permit) = connections.clone().try_acquire_owned() else {
+            warn!("Maximum number of connections reached, ignoring connection");
             continue;
         };
 
-        connections.fetch_add(1, Ordering::Relaxed);
-        if connections.load(Ordering::Relaxed) == CONFIG.limits.max_devices {
+        if connections.available_permits() == 0 {
             warn!("Reached maximum number of connections, new connections will be blocked");
         }
 
-        {
-            let connections = connections.clone();
-            let db = shared_db.clone();
+        let db = shared_db.clone();
+        let sessions = sessions.clone();
+        let tls = tls.clone();
 
-            thread::spawn(move || {
-                debug!("New client: {}", peer_addr);
+        tokio::spawn(async move {
+            // The permit is released when the connection is closed, even if handling it panics.
+            let _permit = permit;
+            debug!("New client: {}", peer_addr);
 
-                match handle_client(client, &db, connections.clone()) {
-                    Ok(()) => {
-                        debug!("{}: Handled successfully", peer_addr);
-                    }
-                    Err(why) => {
-                        error!("{peer_addr}: {why}");
-                    }
+            let handshake = Stream::accept(client, tls.as_ref());
+            let client = match timeout(CONFIG.timeouts.handshake(), handshake).await {
+                Ok(Ok(client)) => client,
+                Ok(Err(why)) => {
+                    error!("{peer_addr}: Failed to set up TLS: {why}");
+                    return;
+                }
+                Err(_) => {
+                    error!("{peer_addr}: TLS handshake timed out");
+                    return;
                 }
+            };
 
-                connections.fetch_sub(1, Ordering::Relaxed);
-            });
-        }
+            match handle_client(client, db.as_ref(), &sessions).await {
+                Ok(()) => {
+                    debug!("{}: Handled successfully", peer_addr);
+                }
+                Err(why) => {
+                    error!("{peer_addr}: {why}");
+                }
+            }
+        });
     }
 }
@@ -0,0 +1,193 @@
+use pwmp_types::{
+    aliases::{MeasurementId, SessionToken},
+    request::{Request, NEW_SESSION},
+    response::Response,
+    NodeId,
+};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// State of a node's session that survives a lost connection.
+#[derive(Debug, Clone, Default)]
+pub struct SessionState {
+    pub last_submit: Option<MeasurementId>,
+    /// Last measurements of nodes relayed by a gateway.
+    pub relayed: HashMap<NodeId, MeasurementId>,
+    /// The last state-changing request and the response it was answered with.
+    /// Used to answer a retried request without processing it twice.
+    pub replay: Option<(Request, Response)>,
+}
+
+struct Session {
+    node: NodeId,
+    state: SessionState,
+    /// `None` while a connection is using the session.
+    expires: Option<Instant>,
+    /// Incremented every time a connection takes over the session.
+    generation: u64,
+}
+
+/// A connection's claim on a session. Once the session is taken over by another connection,
+/// the claim of the previous one is no longer valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lease {
+    token: SessionToken,
+    generation: u64,
+}
+
+pub struct SessionStore {
+    sessions: Mutex<HashMap<SessionToken, Session>>,
+    window: Duration,
+}
+
+impl Lease {
+    pub const fn token(self) -> SessionToken {
+        self.token
+    }
+}
+
+impl SessionStore {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            window,
+        }
+    }
+
+    /// Start a new session for the specified node.
+    pub fn open(&self, node: NodeId) -> Lease {
+        let mut sessions = self.sessions.lock().unwrap();
+        Self::prune(&mut sessions);
+
+        let mut token = rand::random();
+        while token == NEW_SESSION || sessions.contains_key(&token) {
+            token = rand::random();
+        }
+
+        sessions.insert(
+            token,
+            Session {
+                node,
+                state: SessionState::default(),
+                expires: None,
+                generation: 0,
+            },
+        );
+
+        Lease {
+            token,
+            generation: 0,
+        }
+    }
+
+    /// Take over an existing session. Returns `None` if the session does not exist, has
+    /// expired, belongs to a different node or is still used by another connection.
+    pub fn resume(&self, token: SessionToken, node: NodeId) -> Option<(Lease, SessionState)> {
+        let mut sessions = self.sessions.lock().unwrap();
+        Self::prune(&mut sessions);
+
+        let session = sessions.get_mut(&token)?;
+        if session.node != node || session.expires.is_none() {
+            return None;
+        }
+
+        session.expires = None;
+        session.generation += 1;
+
+        let lease = Lease {
+            token,
+            generation: session.generation,
+        };
+
+        Some((lease, session.state.clone()))
+    }
+
+    /// Save the state of an active session.
+    pub fn record(&self, lease: Lease, state: &SessionState) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(&lease.token) {
+            if session.generation == lease.generation {
+                session.state.clone_from(state);
+            }
+        }
+    }
+
+    /// Keep the session around for a while after the connection was lost, so it can be resumed.
+    pub fn suspend(&self, lease: Lease) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(&lease.token) {
+            if session.generation == lease.generation {
+                session.expires = Some(Instant::now() + self.window);
+            }
+        }
+    }
+
+    /// End the session, it can no longer be resumed.
+    pub fn close(&self, lease: Lease) {
+        let mut sessions = self.sessions.lock().unwrap();
+
+        if sessions
+            .get(&lease.token)
+            .is_some_and(|session| session.generation == lease.generation)
+        {
+            sessions.remove(&lease.token);
+        }
+    }
+
+    #[cfg(test)]
+    fn contains(&self, token: SessionToken) -> bool {
+        self.sessions.lock().unwrap().contains_key(&token)
+    }
+
+    fn prune(sessions: &mut HashMap<SessionToken, Session>) {
+        let now = Instant::now();
+        sessions.retain(|_, session| session.expires.is_none_or(|expires| expires > now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionState, SessionStore};
+    use pwmp_types::NodeId;
+    use std::time::Duration;
+
+    const NODE: NodeId = 1;
+
+    #[test]
+    fn resume_suspended() {
+        let sessions = SessionStore::new(Duration::from_secs(30));
+        let lease = sessions.open(NODE);
+
+        assert!(sessions.resume(lease.token(), NODE).is_none());
+
+        sessions.suspend(lease);
+        assert!(sessions.resume(lease.token(), NODE + 1).is_none());
+        assert!(sessions.resume(lease.token(), NODE).is_some());
+    }
+
+    #[test]
+    fn stale_lease() {
+        let sessions = SessionStore::new(Duration::from_secs(30));
+        let old = sessions.open(NODE);
+        sessions.suspend(old);
+        let (new, _) = sessions.resume(old.token(), NODE).unwrap();
+
+        // The previous connection ending must not affect the one that took over.
+        sessions.record(
+            old,
+            &SessionState {
+                last_submit: Some(1),
+                ..Default::default()
+            },
+        );
+        sessions.suspend(old);
+        sessions.close(old);
+        assert!(sessions.contains(new.token()));
+        assert!(sessions.resume(new.token(), NODE).is_none());
+
+        sessions.suspend(new);
+        let (_, state) = sessions.resume(new.token(), NODE).unwrap();
+        assert_eq!(state.last_submit, None);
+    }
+}
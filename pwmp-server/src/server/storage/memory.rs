@@ -0,0 +1,796 @@
+use super::{distance_km, NodeLocation, Storage, StorageError, StorageResult, StoredNotification};
+use crate::server::config::Config;
+use async_trait::async_trait;
+use log::{debug, error, info};
+use pwmp_types::{
+    aliases::{AirPressure, Altitude, BatteryVoltage, Humidity, MeasurementId, Rssi, Temperature},
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity},
+    setting::SettingName,
+    NodeId,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+const DEMO_SEED: &str = include_str!("../../../demo.yml");
+
+/// Keeps everything in memory, so the server can run without a database.
+pub struct MemoryClient(Arc<Mutex<Data>>);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Data {
+    devices: Vec<Device>,
+    measurements: Vec<Measurement>,
+    statistics: Vec<Statistics>,
+    notifications: Vec<Notification>,
+    last_notification_id: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Device {
+    id: NodeId,
+    mac_address: String,
+    location: Option<Location>,
+    gateway: Option<NodeId>,
+    note: Option<String>,
+    settings: Option<Settings>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Location {
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    altitude: Option<Altitude>,
+}
+
+/// Same defaults as the `settings` table.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Settings {
+    battery_ignore: bool,
+    ota: bool,
+    sleep_time: u16,
+    sbop: bool,
+    mute_notifications: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Measurement {
+    id: MeasurementId,
+    node: NodeId,
+    #[serde(with = "time::serde::rfc3339")]
+    when: OffsetDateTime,
+    temperature: Temperature,
+    humidity: Humidity,
+    air_pressure: Option<AirPressure>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Statistics {
+    measurement: MeasurementId,
+    battery: BatteryVoltage,
+    wifi_ssid: String,
+    wifi_rssi: Rssi,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Notification {
+    id: i32,
+    node: NodeId,
+    #[serde(with = "time::serde::rfc3339")]
+    when: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    last_seen: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    expires: Option<OffsetDateTime>,
+    severity: NotificationSeverity,
+    category: NotificationCategory,
+    content: String,
+    occurrences: i32,
+    read: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    read_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Seed {
+    devices: Vec<SeedDevice>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeedDevice {
+    mac: String,
+    #[serde(default)]
+    note: Option<String>,
+    /// MAC address of the gateway relaying for this device.
+    #[serde(default)]
+    gateway: Option<String>,
+    #[serde(default)]
+    location: Option<Location>,
+    #[serde(default)]
+    settings: Option<Settings>,
+}
+
+impl MemoryClient {
+    /// Restore the data from the snapshot if it exists, otherwise load the seed file. If a
+    /// snapshot file is configured, the data is saved to it periodically.
+    pub fn new(config: &Config) -> StorageResult<Self> {
+        let snapshot = config.database.snapshot.as_deref();
+
+        let data = match snapshot.filter(|path| path.exists()) {
+            Some(path) => {
+                info!("Restoring snapshot {}", path.display());
+                serde_json::from_slice(&fs::read(path)?)
+                    .map_err(|why| StorageError::InvalidData(why.to_string().into()))?
+            }
+            None => {
+                let seed = match &config.database.seed {
+                    Some(path) => {
+                        info!("Loading devices from {}", path.display());
+                        fs::read_to_string(path)?
+                    }
+                    None => {
+                        info!("Loading demo devices");
+                        DEMO_SEED.to_string()
+                    }
+                };
+
+                Data::from_seed(&seed)?
+            }
+        };
+
+        info!("{} devices registered", data.devices.len());
+        let client = Self(Arc::new(Mutex::new(data)));
+
+        if let Some(path) = snapshot {
+            client.spawn_snapshots(
+                path.to_path_buf(),
+                Duration::from_secs(config.database.snapshot_interval.max(1)),
+            );
+        }
+
+        Ok(client)
+    }
+
+    fn data(&self) -> MutexGuard<'_, Data> {
+        // The data is never left in an inconsistent state, so it's safe to use after a panic.
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn spawn_snapshots(&self, path: PathBuf, interval: Duration) {
+        let data = self.0.clone();
+
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(interval);
+            timer.tick().await;
+
+            loop {
+                timer.tick().await;
+
+                // Serialize while locked, but write the file after releasing the lock.
+                let json = serde_json::to_vec_pretty(
+                    &*data.lock().unwrap_or_else(|poisoned| poisoned.into_inner()),
+                );
+
+                match json.map_err(Into::into).and_then(|json| save(&path, &json)) {
+                    Ok(()) => debug!("Saved snapshot to {}", path.display()),
+                    Err(why) => error!("Failed to save snapshot to {}: {why}", path.display()),
+                }
+            }
+        });
+    }
+}
+
+impl Data {
+    fn from_seed(seed: &str) -> StorageResult<Self> {
+        let seed: Seed = serde_yaml::from_str(seed)
+            .map_err(|why| StorageError::InvalidData(why.to_string().into()))?;
+        let mut data = Self::default();
+
+        for (i, device) in seed.devices.iter().enumerate() {
+            let mac_address = parse_mac(&device.mac)?;
+            let Ok(id) = NodeId::try_from(i + 1) else {
+                return Err(StorageError::InvalidData("too many devices".into()));
+            };
+
+            if data.device(&mac_address).is_some() {
+                return Err(StorageError::InvalidData(
+                    format!("device {mac_address} is registered twice").into(),
+                ));
+            }
+
+            if let Some(location) = device.location {
+                check_location(location.latitude, location.longitude)?;
+            }
+
+            if device
+                .settings
+                .is_some_and(|settings| settings.sleep_time == 0)
+            {
+                return Err(StorageError::InvalidData(
+                    format!("sleep time of {mac_address} must be positive").into(),
+                ));
+            }
+
+            data.devices.push(Device {
+                id,
+                mac_address,
+                location: device.location,
+                gateway: None,
+                note: device.note.clone(),
+                settings: device.settings,
+            });
+        }
+
+        for (i, device) in seed.devices.iter().enumerate() {
+            let Some(gateway) = &device.gateway else {
+                continue;
+            };
+
+            let Some(gateway) = data.device(&parse_mac(gateway)?).map(|gateway| gateway.id) else {
+                return Err(StorageError::InvalidData(
+                    format!("gateway {gateway} of {} is not registered", device.mac).into(),
+                ));
+            };
+
+            if gateway == data.devices[i].id {
+                return Err(StorageError::InvalidData(
+                    format!("{} cannot be it's own gateway", device.mac).into(),
+                ));
+            }
+
+            data.devices[i].gateway = Some(gateway);
+        }
+
+        Ok(data)
+    }
+
+    fn device(&self, mac_address: &str) -> Option<&Device> {
+        self.devices
+            .iter()
+            .find(|device| device.mac_address == mac_address)
+    }
+
+    fn has_measurement(&self, node: NodeId, when: OffsetDateTime) -> bool {
+        self.measurements
+            .iter()
+            .any(|measurement| measurement.node == node && measurement.when == when)
+    }
+
+    fn post_measurement(
+        &mut self,
+        node: NodeId,
+        when: OffsetDateTime,
+        temperature: Temperature,
+        humidity: Humidity,
+        air_pressure: Option<AirPressure>,
+    ) -> StorageResult<MeasurementId> {
+        if humidity > 100 {
+            return Err(StorageError::Constraint(
+                format!("humidity {humidity}% is out of range").into(),
+            ));
+        }
+
+        if self.has_measurement(node, when) {
+            return Err(StorageError::Constraint(
+                format!("node #{node} already has a measurement taken at {when}").into(),
+            ));
+        }
+
+        // Same range as the `SMALLSERIAL` IDs used with PostgreSQL.
+        let Ok(id) = i16::try_from(self.measurements.len() + 1) else {
+            return Err(StorageError::Constraint(
+                "measurement ID is out of range".into(),
+            ));
+        };
+
+        self.measurements.push(Measurement {
+            id: id as MeasurementId,
+            node,
+            when,
+            temperature,
+            humidity,
+            air_pressure,
+        });
+
+        Ok(id as MeasurementId)
+    }
+
+    fn located_nodes(&self) -> Vec<NodeLocation> {
+        self.devices
+            .iter()
+            .filter_map(|device| {
+                let location = device.location?;
+                let latest = self
+                    .measurements
+                    .iter()
+                    .filter(|measurement| measurement.node == device.id)
+                    .max_by_key(|measurement| measurement.when);
+
+                Some(NodeLocation {
+                    id: device.id,
+                    mac_address: device.mac_address.clone(),
+                    note: device.note.clone(),
+                    latitude: location.latitude,
+                    longitude: location.longitude,
+                    altitude: location.altitude,
+                    distance: None,
+                    when: latest.map(|measurement| primitive(measurement.when)),
+                    temperature: latest.map(|measurement| measurement.temperature),
+                    humidity: latest.map(|measurement| measurement.humidity.into()),
+                    air_pressure: latest
+                        .and_then(|measurement| measurement.air_pressure)
+                        .map(|air_pressure| air_pressure as i16),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            battery_ignore: false,
+            ota: false,
+            sleep_time: 60,
+            sbop: true,
+            mute_notifications: false,
+        }
+    }
+}
+
+impl Settings {
+    fn get(self, setting: SettingName) -> SettingValue {
+        match setting {
+            SettingName::BatteryIgnore => self.battery_ignore.into(),
+            SettingName::Ota => self.ota.into(),
+            SettingName::SleepTime => self.sleep_time.into(),
+            SettingName::Sbop => self.sbop.into(),
+            SettingName::MuteNotifications => self.mute_notifications.into(),
+        }
+    }
+}
+
+impl Notification {
+    fn expired(&self, now: OffsetDateTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryClient {
+    async fn authorize_device(&self, mac: &Mac) -> StorageResult<Option<NodeId>> {
+        Ok(self.data().device(&mac.to_string()).map(|device| device.id))
+    }
+
+    async fn authorize_relayed_device(
+        &self,
+        mac: &Mac,
+        gateway: NodeId,
+    ) -> StorageResult<Option<NodeId>> {
+        Ok(self
+            .data()
+            .device(&mac.to_string())
+            .filter(|device| device.gateway == Some(gateway))
+            .map(|device| device.id))
+    }
+
+    async fn post_results(
+        &self,
+        node: NodeId,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<MeasurementId> {
+        self.data()
+            .post_measurement(node, OffsetDateTime::now_utc(), temp, hum, air_p)
+    }
+
+    async fn post_queued_results(
+        &self,
+        node: NodeId,
+        measured_at: i64,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<Option<MeasurementId>> {
+        let Ok(when) = OffsetDateTime::from_unix_timestamp(measured_at) else {
+            return Err(StorageError::Constraint(
+                format!("measurement time {measured_at} is out of range").into(),
+            ));
+        };
+
+        let mut data = self.data();
+        if data.has_measurement(node, when) {
+            return Ok(None);
+        }
+
+        data.post_measurement(node, when, temp, hum, air_p)
+            .map(Some)
+    }
+
+    async fn post_stats(
+        &self,
+        measurement: MeasurementId,
+        battery: &BatteryVoltage,
+        wifi_ssid: &str,
+        wifi_rssi: Rssi,
+    ) -> StorageResult<()> {
+        let mut data = self.data();
+
+        if !data
+            .measurements
+            .iter()
+            .any(|stored| stored.id == measurement)
+        {
+            return Err(StorageError::Constraint(
+                format!("measurement {measurement} does not exist").into(),
+            ));
+        }
+
+        if battery.is_sign_negative() || battery.is_zero() || wifi_ssid.len() > 32 {
+            return Err(StorageError::Constraint("invalid statistics".into()));
+        }
+
+        data.statistics.push(Statistics {
+            measurement,
+            battery: *battery,
+            wifi_ssid: wifi_ssid.to_string(),
+            wifi_rssi,
+        });
+
+        Ok(())
+    }
+
+    async fn set_location(
+        &self,
+        id: NodeId,
+        latitude: f64,
+        longitude: f64,
+        altitude: Option<Altitude>,
+    ) -> StorageResult<()> {
+        check_location(latitude, longitude).map_err(|_| {
+            StorageError::Constraint(format!("location {latitude}, {longitude} is invalid").into())
+        })?;
+
+        if let Some(device) = self
+            .data()
+            .devices
+            .iter_mut()
+            .find(|device| device.id == id)
+        {
+            device.location = Some(Location {
+                latitude,
+                longitude,
+                altitude,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn nodes_within_radius(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> StorageResult<Vec<NodeLocation>> {
+        let mut nodes = self.data().located_nodes();
+
+        for node in &mut nodes {
+            node.distance = Some(distance_km(
+                (latitude, longitude),
+                (node.latitude, node.longitude),
+            ));
+        }
+
+        nodes.retain(|node| node.distance.is_some_and(|distance| distance <= radius_km));
+        nodes.sort_by(|a, b| {
+            a.distance
+                .unwrap_or_default()
+                .total_cmp(&b.distance.unwrap_or_default())
+        });
+
+        Ok(nodes)
+    }
+
+    async fn nodes_within_box(
+        &self,
+        (min_latitude, min_longitude): (f64, f64),
+        (max_latitude, max_longitude): (f64, f64),
+    ) -> StorageResult<Vec<NodeLocation>> {
+        let mut nodes = self.data().located_nodes();
+
+        nodes.retain(|node| {
+            (min_latitude..=max_latitude).contains(&node.latitude)
+                && (min_longitude..=max_longitude).contains(&node.longitude)
+        });
+
+        Ok(nodes)
+    }
+
+    async fn create_notification(
+        &self,
+        id: NodeId,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+        expiry: Option<i32>,
+        deduplicate: bool,
+    ) -> StorageResult<()> {
+        let now = OffsetDateTime::now_utc();
+        let expires = expiry.map(|expiry| now + time::Duration::seconds(expiry.into()));
+        let mut data = self.data();
+
+        let existing = data.notifications.iter_mut().find(|notification| {
+            deduplicate
+                && notification.node == id
+                && notification.severity == severity
+                && notification.category == category
+                && notification.content == content
+                && !notification.read
+                && !notification.expired(now)
+        });
+
+        if let Some(notification) = existing {
+            notification.occurrences += 1;
+            notification.last_seen = now;
+            notification.expires = expires;
+            return Ok(());
+        }
+
+        data.last_notification_id += 1;
+        let notification = Notification {
+            id: data.last_notification_id,
+            node: id,
+            when: now,
+            last_seen: now,
+            expires,
+            severity,
+            category,
+            content: content.to_string(),
+            occurrences: 1,
+            read: false,
+            read_at: None,
+        };
+        data.notifications.push(notification);
+
+        Ok(())
+    }
+
+    async fn list_notifications(
+        &self,
+        unread_only: bool,
+    ) -> StorageResult<Vec<StoredNotification>> {
+        let now = OffsetDateTime::now_utc();
+        let data = self.data();
+
+        let mut notifications = data
+            .notifications
+            .iter()
+            .filter(|notification| !(unread_only && notification.read))
+            .filter(|notification| !notification.expired(now))
+            .collect::<Vec<_>>();
+        notifications.sort_by_key(|notification| std::cmp::Reverse(notification.last_seen));
+
+        Ok(notifications
+            .into_iter()
+            .map(|notification| StoredNotification {
+                id: notification.id,
+                mac_address: data
+                    .devices
+                    .iter()
+                    .find(|device| device.id == notification.node)
+                    .map(|device| device.mac_address.clone())
+                    .unwrap_or_default(),
+                when: primitive(notification.when),
+                last_seen: primitive(notification.last_seen),
+                severity: notification.severity.name().to_string(),
+                category: notification.category.name().to_string(),
+                content: notification.content.clone(),
+                occurrences: notification.occurrences,
+                read: notification.read,
+            })
+            .collect())
+    }
+
+    async fn mark_notification_read(&self, id: i32) -> StorageResult<bool> {
+        let mut data = self.data();
+        let Some(notification) = data
+            .notifications
+            .iter_mut()
+            .find(|notification| notification.id == id && !notification.read)
+        else {
+            return Ok(false);
+        };
+
+        notification.read = true;
+        notification.read_at = Some(OffsetDateTime::now_utc());
+        Ok(true)
+    }
+
+    async fn purge_notifications(&self) -> StorageResult<u64> {
+        let now = OffsetDateTime::now_utc();
+        let mut data = self.data();
+        let before = data.notifications.len();
+
+        data.notifications
+            .retain(|notification| !notification.expired(now));
+
+        Ok((before - data.notifications.len()) as u64)
+    }
+
+    async fn get_settings(
+        &self,
+        id: NodeId,
+        settings: &[SettingName],
+    ) -> StorageResult<Vec<Option<SettingValue>>> {
+        let stored = self
+            .data()
+            .devices
+            .iter()
+            .find(|device| device.id == id)
+            .and_then(|device| device.settings);
+
+        Ok(settings
+            .iter()
+            .map(|setting| stored.map(|stored| stored.get(*setting)))
+            .collect())
+    }
+}
+
+fn parse_mac(mac: &str) -> StorageResult<String> {
+    Mac::from_str(mac)
+        .map(|mac| mac.to_string())
+        .map_err(|_| StorageError::InvalidData(format!("invalid MAC address {mac}").into()))
+}
+
+fn check_location(latitude: f64, longitude: f64) -> StorageResult<()> {
+    if (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidData(
+            format!("location {latitude}, {longitude} is out of range").into(),
+        ))
+    }
+}
+
+/// Timestamps are kept in UTC, like the ones stored by the SQLite backend.
+fn primitive(time: OffsetDateTime) -> PrimitiveDateTime {
+    PrimitiveDateTime::new(time.date(), time.time())
+}
+
+/// Write the file atomically, so a crash never leaves a partial snapshot behind.
+fn save(path: &Path, json: &[u8]) -> io::Result<()> {
+    let temp = path.with_extension("tmp");
+
+    fs::write(&temp, json)?;
+    fs::rename(temp, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Data, MemoryClient, DEMO_SEED};
+    use crate::server::storage::{Storage, StorageError};
+    use pwmp_types::{dec, mac::Mac, multitype::SettingValue, setting::SettingName, Decimal};
+    use std::sync::{Arc, Mutex};
+
+    const GARDEN: Mac = Mac::new(1, 2, 3, 4, 5, 6);
+    const GATEWAY: Mac = Mac::new(1, 2, 3, 4, 5, 7);
+    const GREENHOUSE: Mac = Mac::new(1, 2, 3, 4, 5, 8);
+
+    fn demo() -> MemoryClient {
+        MemoryClient(Arc::new(Mutex::new(Data::from_seed(DEMO_SEED).unwrap())))
+    }
+
+    #[tokio::test]
+    async fn demo_devices() {
+        let db = demo();
+        let garden = db.authorize_device(&GARDEN).await.unwrap().unwrap();
+        let gateway = db.authorize_device(&GATEWAY).await.unwrap().unwrap();
+
+        assert!(db
+            .authorize_relayed_device(&GREENHOUSE, gateway)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            db.authorize_relayed_device(&GREENHOUSE, garden)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            db.get_settings(garden, &[SettingName::SleepTime, SettingName::Sbop])
+                .await
+                .unwrap(),
+            [
+                Some(SettingValue::Number(60)),
+                Some(SettingValue::Boolean(true))
+            ]
+        );
+        assert_eq!(
+            db.nodes_within_radius(46.05, 14.5, 10.0)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_queued_measurement() {
+        let db = demo();
+        let node = db.authorize_device(&GARDEN).await.unwrap().unwrap();
+
+        let other = db.authorize_device(&GATEWAY).await.unwrap().unwrap();
+
+        let measurement = db
+            .post_queued_results(node, 1_700_000_000, dec!(12.25), 80, None)
+            .await
+            .unwrap()
+            .unwrap();
+        db.post_stats(measurement, &dec!(3.81), "Weather", -67)
+            .await
+            .unwrap();
+        let duplicate = db
+            .post_queued_results(node, 1_700_000_000, dec!(12.25), 80, None)
+            .await
+            .unwrap();
+        let other = db
+            .post_queued_results(other, 1_700_000_000, dec!(12.25), 80, None)
+            .await
+            .unwrap();
+
+        assert_eq!(duplicate, None);
+        assert!(other.is_some());
+    }
+
+    #[test]
+    fn invalid_seed() {
+        let duplicate = "devices: [{ mac: '01:02:03:04:05:06' }, { mac: '01:02:03:04:05:06' }]";
+        let unknown_gateway =
+            "devices: [{ mac: '01:02:03:04:05:06', gateway: '0A:0B:0C:0D:0E:0F' }]";
+
+        for seed in [duplicate, unknown_gateway, "devices: [{ mac: nope }]"] {
+            assert!(matches!(
+                Data::from_seed(seed),
+                Err(StorageError::InvalidData(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot() {
+        let db = demo();
+        let node = db.authorize_device(&GARDEN).await.unwrap().unwrap();
+        db.post_results(node, dec!(21.50), 45, Some(1013))
+            .await
+            .unwrap();
+
+        let json = serde_json::to_vec(&*db.data()).unwrap();
+        let restored = MemoryClient(Arc::new(Mutex::new(serde_json::from_slice(&json).unwrap())));
+        let nodes = restored
+            .nodes_within_radius(46.05, 14.5, 10.0)
+            .await
+            .unwrap();
+
+        assert_eq!(nodes[0].temperature, Some(dec!(21.50)));
+        assert_eq!(nodes[0].humidity, Some(45));
+    }
+}
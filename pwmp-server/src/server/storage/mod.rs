@@ -0,0 +1,197 @@
+use async_trait::async_trait;
+use pwmp_types::{
+    aliases::{AirPressure, Altitude, BatteryVoltage, Humidity, MeasurementId, Rssi, Temperature},
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity},
+    setting::SettingName,
+    NodeId,
+};
+use sqlx::{error::ErrorKind, migrate::MigrateError};
+use std::io;
+use time::PrimitiveDateTime;
+
+pub mod memory;
+pub mod postgres;
+pub mod schema;
+pub mod sqlite;
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    /// The data violates a constraint of the storage, for eg. a duplicate measurement time.
+    /// Retrying the same request will fail again.
+    #[error("Constraint violated: {0}")]
+    Constraint(Box<str>),
+    /// Failed to communicate with the database.
+    #[error("Database error: {0}")]
+    Database(#[source] sqlx::Error),
+    /// The database schema is not the version the server expects.
+    #[error("Incompatible database schema: {0}")]
+    Schema(Box<str>),
+    /// Failed to apply migrations.
+    #[error("Migration failed: {0}")]
+    Migration(#[from] MigrateError),
+    /// Failed to read or write a seed or snapshot file.
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    /// A seed or snapshot file is malformed.
+    #[error("Invalid data: {0}")]
+    InvalidData(Box<str>),
+}
+
+impl From<sqlx::Error> for StorageError {
+    fn from(value: sqlx::Error) -> Self {
+        match value {
+            sqlx::Error::Database(why) if why.kind() != ErrorKind::Other => {
+                Self::Constraint(why.message().into())
+            }
+            other => Self::Database(other),
+        }
+    }
+}
+
+/// Where the server keeps devices, their measurements, statistics, settings and notifications.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Returns the ID of the node with the given MAC address, if it's registered.
+    async fn authorize_device(&self, mac: &Mac) -> StorageResult<Option<NodeId>>;
+
+    /// Returns the ID of the node with the given MAC address, if it's assigned to `gateway`.
+    async fn authorize_relayed_device(
+        &self,
+        mac: &Mac,
+        gateway: NodeId,
+    ) -> StorageResult<Option<NodeId>>;
+
+    async fn post_results(
+        &self,
+        node: NodeId,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<MeasurementId>;
+
+    /// Store a measurement taken at `measured_at` (UNIX seconds). Returns `None` if the node
+    /// already has a measurement taken at that time, since it was most likely posted again.
+    async fn post_queued_results(
+        &self,
+        node: NodeId,
+        measured_at: i64,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<Option<MeasurementId>>;
+
+    async fn post_stats(
+        &self,
+        measurement: MeasurementId,
+        battery: &BatteryVoltage,
+        wifi_ssid: &str,
+        wifi_rssi: Rssi,
+    ) -> StorageResult<()>;
+
+    async fn set_location(
+        &self,
+        id: NodeId,
+        latitude: f64,
+        longitude: f64,
+        altitude: Option<Altitude>,
+    ) -> StorageResult<()>;
+
+    /// Returns located nodes within `radius_km` of a point, nearest first.
+    async fn nodes_within_radius(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> StorageResult<Vec<NodeLocation>>;
+
+    /// Returns located nodes within a bounding box, ordered by their ID.
+    async fn nodes_within_box(
+        &self,
+        min: (f64, f64),
+        max: (f64, f64),
+    ) -> StorageResult<Vec<NodeLocation>>;
+
+    /// Store a notification, expiring after `expiry` seconds. If `deduplicate` is set, an
+    /// identical unread notification of the node is updated instead.
+    async fn create_notification(
+        &self,
+        id: NodeId,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+        expiry: Option<i32>,
+        deduplicate: bool,
+    ) -> StorageResult<()>;
+
+    /// Returns notifications which have not expired, most recently seen first.
+    async fn list_notifications(&self, unread_only: bool)
+        -> StorageResult<Vec<StoredNotification>>;
+
+    /// Returns whether the notification existed and was unread.
+    async fn mark_notification_read(&self, id: i32) -> StorageResult<bool>;
+
+    /// Delete expired notifications, returning how many were deleted.
+    async fn purge_notifications(&self) -> StorageResult<u64>;
+
+    /// Returns the values of the requested settings, in order. Settings that are not set
+    /// for the node are `None`.
+    async fn get_settings(
+        &self,
+        id: NodeId,
+        settings: &[SettingName],
+    ) -> StorageResult<Vec<Option<SettingValue>>>;
+
+    async fn notifications_muted(&self, id: NodeId) -> StorageResult<bool> {
+        Ok(self
+            .get_settings(id, &[SettingName::MuteNotifications])
+            .await?
+            .into_iter()
+            .next()
+            .flatten()
+            .unwrap_or_else(|| SettingName::MuteNotifications.default_value())
+            .as_bool()
+            .unwrap_or_default())
+    }
+}
+
+/// Returns the great-circle distance between two points in kilometers.
+pub(super) fn distance_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    let a = ((lat2 - lat1).to_radians() / 2.0).sin().powi(2)
+        + lat1.to_radians().cos()
+            * lat2.to_radians().cos()
+            * ((lon2 - lon1).to_radians() / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+pub struct NodeLocation {
+    pub id: NodeId,
+    pub mac_address: String,
+    pub note: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<i16>,
+    pub distance: Option<f64>,
+    pub when: Option<PrimitiveDateTime>,
+    pub temperature: Option<Temperature>,
+    pub humidity: Option<i16>,
+    pub air_pressure: Option<i16>,
+}
+
+pub struct StoredNotification {
+    pub id: i32,
+    pub mac_address: String,
+    pub when: PrimitiveDateTime,
+    pub last_seen: PrimitiveDateTime,
+    pub severity: String,
+    pub category: String,
+    pub content: String,
+    pub occurrences: i32,
+    pub read: bool,
+}
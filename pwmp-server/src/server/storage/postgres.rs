@@ -0,0 +1,471 @@
+use super::{
+    schema::{self, Schema, SchemaStatus},
+    NodeLocation, Storage, StorageError, StorageResult, StoredNotification,
+};
+use crate::server::config::Config;
+use async_trait::async_trait;
+use log::warn;
+use pwmp_types::{
+    aliases::{AirPressure, Altitude, BatteryVoltage, Humidity, MeasurementId, Rssi, Temperature},
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity},
+    setting::SettingName,
+    NodeId,
+};
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    postgres::{PgConnectOptions, PgPoolOptions, PgSslMode},
+    Pool, Postgres, Row,
+};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+};
+
+static MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// What each migration adds to the schema, used to find out the version of databases set up
+/// before migrations were introduced.
+const MARKERS: &[(i64, &[Marker])] = &[
+    (
+        1,
+        &[
+            Marker::Column("devices", "mac_address"),
+            Marker::Column("devices", "location"),
+            Marker::Column("measurements", "when"),
+            Marker::Column("measurements", "air_pressure"),
+            Marker::Column("statistics", "measurement"),
+            Marker::Column("settings", "device_specific"),
+            Marker::Column("notifications", "content"),
+            Marker::Column("web_users", "username"),
+            Marker::Column("web_sessions", "token"),
+        ],
+    ),
+    (
+        2,
+        &[
+            Marker::Column("notifications", "last_seen"),
+            Marker::Column("notifications", "expires"),
+            Marker::Column("notifications", "severity"),
+            Marker::Column("notifications", "category"),
+            Marker::Column("notifications", "occurrences"),
+            Marker::Column("notifications", "read_at"),
+        ],
+    ),
+    (3, &[Marker::Column("devices", "altitude")]),
+    (4, &[Marker::Column("devices", "gateway")]),
+    (5, &[Marker::Constraint("measurements_node_when_key")]),
+];
+
+enum Marker {
+    Column(&'static str, &'static str),
+    Constraint(&'static str),
+}
+
+pub struct DatabaseClient(Pool<Postgres>);
+
+impl DatabaseClient {
+    /// Connect to the database, failing if the schema is not the version the server expects.
+    pub async fn new(config: &Config) -> StorageResult<Self> {
+        let db = Self::connect(config).await?;
+        db.schema_status().await?.check_current()?;
+
+        Ok(db)
+    }
+
+    /// Connect to the database without checking the schema.
+    pub async fn connect(config: &Config) -> sqlx::Result<Self> {
+        let mut opts = PgConnectOptions::new()
+            .host(&config.database.host)
+            .port(config.database.port)
+            .username(&config.database.user)
+            .password(&config.database.password)
+            .database(&config.database.name);
+
+        if config.database.ssl {
+            opts = opts.ssl_mode(PgSslMode::Require);
+        }
+
+        let pool = PgPoolOptions::new()
+            .max_connections(3)
+            .connect_with(opts)
+            .await?;
+
+        Ok(Self(pool))
+    }
+
+    const fn pool(&self) -> &Pool<Postgres> {
+        &self.0
+    }
+
+    /// Databases set up by running the schema script manually, before migrations were
+    /// introduced, are marked as having the migrations applied that their schema matches.
+    /// Schemas that don't match any version are refused.
+    async fn adopt_existing_schema(&self) -> StorageResult<()> {
+        let columns: HashSet<(String, String)> = sqlx::query_as(
+            "SELECT table_name::TEXT, column_name::TEXT FROM information_schema.columns
+             WHERE table_schema = current_schema()",
+        )
+        .fetch_all(self.pool())
+        .await?
+        .into_iter()
+        .collect();
+        let constraints: HashSet<String> = sqlx::query_scalar(
+            "SELECT conname::TEXT FROM pg_constraint
+             WHERE connamespace = current_schema()::REGNAMESPACE",
+        )
+        .fetch_all(self.pool())
+        .await?
+        .into_iter()
+        .collect();
+
+        let exists = |marker: &Marker| match marker {
+            Marker::Column(table, column) => {
+                columns.contains(&((*table).to_string(), (*column).to_string()))
+            }
+            Marker::Constraint(name) => constraints.contains(*name),
+        };
+
+        // Each version must be either fully applied or not at all, and only directly after
+        // the previous one.
+        let mut version = None;
+        for (migration, markers) in MARKERS {
+            let applied = markers.iter().filter(|marker| exists(marker)).count();
+            if applied == 0 {
+                continue;
+            }
+
+            if let Some(missing) = markers.iter().find(|marker| !exists(marker)) {
+                return Err(unknown_schema(&format!("{missing} is missing")));
+            }
+
+            if *migration != version.map_or(1, |version| version + 1) {
+                return Err(unknown_schema(&format!(
+                    "{} exists, but earlier changes are missing",
+                    markers[0]
+                )));
+            }
+
+            version = Some(*migration);
+        }
+
+        let Some(version) = version else {
+            return Ok(());
+        };
+
+        warn!("Found a schema created without migrations, assuming it's version {version}");
+        let mut tx = self.pool().begin().await?;
+        tx.ensure_migrations_table().await?;
+
+        for migration in MIGRATIONS
+            .iter()
+            .take_while(|migration| migration.version <= version)
+        {
+            sqlx::query(
+                "INSERT INTO _sqlx_migrations(version, description, success, checksum, execution_time)
+                 VALUES ($1, $2, TRUE, $3, 0)",
+            )
+            .bind(migration.version)
+            .bind(&*migration.description)
+            .bind(&*migration.checksum)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+impl Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Column(table, column) => write!(f, "column `{table}.{column}`"),
+            Self::Constraint(name) => write!(f, "constraint `{name}`"),
+        }
+    }
+}
+
+fn unknown_schema(reason: &str) -> StorageError {
+    StorageError::Schema(
+        format!("the existing schema doesn't match any known version, {reason}").into(),
+    )
+}
+
+#[async_trait]
+impl Schema for DatabaseClient {
+    async fn schema_status(&self) -> StorageResult<SchemaStatus> {
+        let table_exists = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(self.pool())
+            .await?;
+
+        schema::status(self.pool(), &MIGRATIONS, table_exists).await
+    }
+
+    async fn migrate(&self) -> StorageResult<usize> {
+        if self.schema_status().await?.version().is_none() {
+            self.adopt_existing_schema().await?;
+        }
+
+        schema::migrate(self.pool(), &MIGRATIONS).await
+    }
+
+    async fn reset(&self) -> StorageResult<()> {
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT quote_ident(tablename) FROM pg_tables WHERE schemaname = current_schema()",
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        if !tables.is_empty() {
+            sqlx::query(&format!("DROP TABLE {} CASCADE", tables.join(", ")))
+                .execute(self.pool())
+                .await?;
+        }
+
+        schema::migrate(self.pool(), &MIGRATIONS).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for DatabaseClient {
+    async fn authorize_device(&self, mac: &Mac) -> StorageResult<Option<NodeId>> {
+        let mac = mac.to_string();
+
+        Ok(sqlx::query_file!("queries/get_device_by_mac.sql", mac)
+            .fetch_optional(self.pool())
+            .await?
+            .map(|res| res.id))
+    }
+
+    async fn authorize_relayed_device(
+        &self,
+        mac: &Mac,
+        gateway: NodeId,
+    ) -> StorageResult<Option<NodeId>> {
+        let mac = mac.to_string();
+
+        Ok(
+            sqlx::query_file!("queries/get_relayed_device_by_mac.sql", mac, gateway)
+                .fetch_optional(self.pool())
+                .await?
+                .map(|res| res.id),
+        )
+    }
+
+    #[allow(clippy::cast_possible_wrap, clippy::cast_lossless)]
+    async fn post_results(
+        &self,
+        node: NodeId,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<MeasurementId> {
+        Ok(sqlx::query_file!(
+            "queries/post_results.sql",
+            node,
+            temp,
+            hum as i16,
+            air_p.map(|value| value as i16)
+        )
+        .fetch_one(self.pool())
+        .await?
+        .id as u16)
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_sign_loss,
+        clippy::cast_lossless
+    )]
+    async fn post_queued_results(
+        &self,
+        node: NodeId,
+        measured_at: i64,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<Option<MeasurementId>> {
+        Ok(sqlx::query_file!(
+            "queries/post_queued_results.sql",
+            node,
+            measured_at,
+            temp,
+            hum as i16,
+            air_p.map(|value| value as i16)
+        )
+        .fetch_optional(self.pool())
+        .await?
+        .map(|row| row.id as u16))
+    }
+
+    async fn post_stats(
+        &self,
+        measurement: MeasurementId,
+        battery: &BatteryVoltage,
+        wifi_ssid: &str,
+        wifi_rssi: Rssi,
+    ) -> StorageResult<()> {
+        sqlx::query_file!(
+            "queries/post_stats.sql",
+            measurement as i16,
+            battery,
+            wifi_ssid,
+            wifi_rssi as i16
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_location(
+        &self,
+        id: NodeId,
+        latitude: f64,
+        longitude: f64,
+        altitude: Option<Altitude>,
+    ) -> StorageResult<()> {
+        sqlx::query_file!(
+            "queries/set_location.sql",
+            id,
+            latitude,
+            longitude,
+            altitude
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn nodes_within_radius(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> StorageResult<Vec<NodeLocation>> {
+        Ok(sqlx::query_file_as!(
+            NodeLocation,
+            "queries/nodes_within_radius.sql",
+            latitude,
+            longitude,
+            radius_km
+        )
+        .fetch_all(self.pool())
+        .await?)
+    }
+
+    async fn nodes_within_box(
+        &self,
+        (min_latitude, min_longitude): (f64, f64),
+        (max_latitude, max_longitude): (f64, f64),
+    ) -> StorageResult<Vec<NodeLocation>> {
+        Ok(sqlx::query_file_as!(
+            NodeLocation,
+            "queries/nodes_within_box.sql",
+            min_latitude,
+            min_longitude,
+            max_latitude,
+            max_longitude
+        )
+        .fetch_all(self.pool())
+        .await?)
+    }
+
+    async fn create_notification(
+        &self,
+        id: NodeId,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+        expiry: Option<i32>,
+        deduplicate: bool,
+    ) -> StorageResult<()> {
+        sqlx::query_file!(
+            "queries/create_notification.sql",
+            id,
+            severity.name(),
+            category.name(),
+            content,
+            expiry,
+            deduplicate
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_notifications(
+        &self,
+        unread_only: bool,
+    ) -> StorageResult<Vec<StoredNotification>> {
+        Ok(sqlx::query_file_as!(
+            StoredNotification,
+            "queries/list_notifications.sql",
+            unread_only
+        )
+        .fetch_all(self.pool())
+        .await?)
+    }
+
+    async fn mark_notification_read(&self, id: i32) -> StorageResult<bool> {
+        Ok(sqlx::query_file!("queries/mark_notification_read.sql", id)
+            .execute(self.pool())
+            .await?
+            .rows_affected()
+            > 0)
+    }
+
+    async fn purge_notifications(&self) -> StorageResult<u64> {
+        Ok(sqlx::query_file!("queries/purge_notifications.sql")
+            .execute(self.pool())
+            .await?
+            .rows_affected())
+    }
+
+    async fn get_settings(
+        &self,
+        id: NodeId,
+        settings: &[SettingName],
+    ) -> StorageResult<Vec<Option<SettingValue>>> {
+        let mut results = vec![None; settings.len()];
+
+        if settings.is_empty() {
+            return Ok(results);
+        }
+
+        let columns = settings
+            .iter()
+            .map(|setting| setting.name())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        let query = format!("SELECT {columns} FROM settings WHERE node = $1");
+
+        let Some(row) = sqlx::query(&query)
+            .bind(id)
+            .fetch_optional(self.pool())
+            .await?
+        else {
+            return Ok(results);
+        };
+
+        for (i, setting) in settings.iter().enumerate() {
+            let value: SettingValue = match setting {
+                SettingName::BatteryIgnore
+                | SettingName::Ota
+                | SettingName::Sbop
+                | SettingName::MuteNotifications => row.try_get::<bool, _>(i)?.into(),
+                SettingName::SleepTime => (row.try_get::<i16, _>(i)? as u16).into(),
+            };
+
+            results[i] = Some(value);
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,163 @@
+use super::{StorageError, StorageResult};
+use async_trait::async_trait;
+use sqlx::{
+    migrate::{Migrate, Migration, Migrator},
+    Database, Pool,
+};
+
+/// Storage backends whose schema is versioned using the migrations built into the server.
+#[async_trait]
+pub trait Schema: Send + Sync {
+    async fn schema_status(&self) -> StorageResult<SchemaStatus>;
+
+    /// Apply pending migrations, returning how many were applied.
+    async fn migrate(&self) -> StorageResult<usize>;
+
+    /// Delete all tables, including their data, and apply all migrations again.
+    async fn reset(&self) -> StorageResult<()>;
+}
+
+pub struct SchemaStatus {
+    /// Migrations built into the server, and whether they were applied.
+    pub migrations: Vec<(&'static Migration, bool)>,
+    /// Versions applied to the database which this server doesn't know about.
+    pub unknown: Vec<i64>,
+    /// Versions which were changed after they were applied.
+    pub modified: Vec<i64>,
+}
+
+impl SchemaStatus {
+    /// Returns the highest applied version, if any.
+    pub fn version(&self) -> Option<i64> {
+        self.migrations
+            .iter()
+            .filter(|(_, applied)| *applied)
+            .map(|(migration, _)| migration.version)
+            .chain(self.unknown.iter().copied())
+            .max()
+    }
+
+    /// Returns the version the server expects.
+    pub fn latest(&self) -> i64 {
+        self.migrations
+            .last()
+            .map_or(0, |(migration, _)| migration.version)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &Migration> {
+        self.migrations
+            .iter()
+            .filter(|(_, applied)| !applied)
+            .map(|(migration, _)| *migration)
+    }
+
+    /// Fails if the database was migrated by a newer server, or the applied migrations were
+    /// changed since.
+    pub fn check_supported(&self) -> StorageResult<()> {
+        if !self.unknown.is_empty() {
+            return Err(StorageError::Schema(
+                format!(
+                    "version {} is newer than the latest supported version {}, upgrade the server",
+                    self.version().unwrap_or_default(),
+                    self.latest()
+                )
+                .into(),
+            ));
+        }
+
+        if let Some(version) = self.modified.first() {
+            return Err(StorageError::Schema(
+                format!("migration {version} was modified after it was applied").into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fails unless the schema is exactly the version the server expects.
+    pub fn check_current(&self) -> StorageResult<()> {
+        self.check_supported()?;
+
+        if self.pending().next().is_none() {
+            return Ok(());
+        }
+
+        let message = match self.version() {
+            Some(version) => format!(
+                "version {version} is outdated, run `pwmp-server db migrate` to upgrade to version {}",
+                self.latest()
+            ),
+            None => "no version is applied, run `pwmp-server db migrate` to set it up".to_string(),
+        };
+
+        Err(StorageError::Schema(message.into()))
+    }
+}
+
+/// Compare the migrations applied to the database with the ones built into the server. If the
+/// migrations table doesn't exist, no migrations were applied, it's not created here so checking
+/// the status never writes to the database.
+pub async fn status<DB>(
+    pool: &Pool<DB>,
+    migrator: &'static Migrator,
+    table_exists: bool,
+) -> StorageResult<SchemaStatus>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    let applied = if table_exists {
+        pool.acquire().await?.list_applied_migrations().await?
+    } else {
+        Vec::new()
+    };
+
+    let migrations = migrator
+        .iter()
+        .map(|migration| {
+            let applied = applied
+                .iter()
+                .any(|applied| applied.version == migration.version);
+
+            (migration, applied)
+        })
+        .collect();
+
+    let unknown = applied
+        .iter()
+        .filter(|applied| !migrator.version_exists(applied.version))
+        .map(|applied| applied.version)
+        .collect();
+
+    let modified = applied
+        .iter()
+        .filter(|applied| {
+            migrator.iter().any(|migration| {
+                migration.version == applied.version && migration.checksum != applied.checksum
+            })
+        })
+        .map(|applied| applied.version)
+        .collect();
+
+    Ok(SchemaStatus {
+        migrations,
+        unknown,
+        modified,
+    })
+}
+
+/// Apply pending migrations, refusing to touch a schema that's newer than the server.
+pub async fn migrate<DB>(pool: &Pool<DB>, migrator: &'static Migrator) -> StorageResult<usize>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    pool.acquire().await?.ensure_migrations_table().await?;
+    let status = status(pool, migrator, true).await?;
+    status.check_supported()?;
+
+    let pending = status.pending().count();
+    migrator.run(pool).await?;
+
+    Ok(pending)
+}
@@ -0,0 +1,663 @@
+use super::{
+    distance_km,
+    schema::{self, Schema, SchemaStatus},
+    NodeLocation, Storage, StorageError, StorageResult, StoredNotification,
+};
+use crate::server::config::Config;
+use async_trait::async_trait;
+use pwmp_types::{
+    aliases::{AirPressure, Altitude, BatteryVoltage, Humidity, MeasurementId, Rssi, Temperature},
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity},
+    setting::SettingName,
+    Decimal, NodeId,
+};
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow},
+    Pool, Row, Sqlite,
+};
+
+static MIGRATIONS: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+pub struct SqliteClient(Pool<Sqlite>);
+
+impl SqliteClient {
+    /// Open the database file, failing if the schema is not the version the server expects.
+    pub async fn new(config: &Config) -> StorageResult<Self> {
+        let db = Self::connect(config).await?;
+        db.schema_status().await?.check_current()?;
+
+        Ok(db)
+    }
+
+    /// Open the database file without touching the schema.
+    pub async fn connect(config: &Config) -> sqlx::Result<Self> {
+        let opts = SqliteConnectOptions::new()
+            .filename(&config.database.path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(3)
+            .connect_with(opts)
+            .await?;
+
+        Ok(Self(pool))
+    }
+
+    const fn pool(&self) -> &Pool<Sqlite> {
+        &self.0
+    }
+
+    async fn located_nodes(&self) -> StorageResult<Vec<NodeLocation>> {
+        let rows = sqlx::query(include_str!("../../../queries/sqlite/located_nodes.sql"))
+            .fetch_all(self.pool())
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(node_location)
+            .collect::<sqlx::Result<_>>()?)
+    }
+
+    async fn insert_measurement(
+        &self,
+        query: &str,
+        node: NodeId,
+        measured_at: Option<i64>,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<Option<MeasurementId>> {
+        let mut tx = self.pool().begin().await?;
+        let mut query = sqlx::query(query).bind(node);
+
+        if let Some(measured_at) = measured_at {
+            query = query.bind(measured_at);
+        }
+
+        // Nothing is returned if the query skipped a duplicate measurement.
+        let Some(row) = query
+            .bind(to_real(temp)?)
+            .bind(hum)
+            .bind(air_p)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let id: i64 = row.try_get(0)?;
+
+        // Same range as the `SMALLSERIAL` IDs used with PostgreSQL. The transaction is rolled
+        // back when dropped, so the measurement is not stored.
+        let Ok(id) = i16::try_from(id) else {
+            return Err(StorageError::Constraint(
+                "measurement ID is out of range".into(),
+            ));
+        };
+
+        tx.commit().await?;
+        Ok(Some(id as MeasurementId))
+    }
+}
+
+#[async_trait]
+impl Schema for SqliteClient {
+    async fn schema_status(&self) -> StorageResult<SchemaStatus> {
+        let table_exists = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'
+             )",
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        schema::status(self.pool(), &MIGRATIONS, table_exists).await
+    }
+
+    async fn migrate(&self) -> StorageResult<usize> {
+        schema::migrate(self.pool(), &MIGRATIONS).await
+    }
+
+    async fn reset(&self) -> StorageResult<()> {
+        // Newest tables first, so no table is dropped while others still reference it.
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY rowid DESC",
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        for table in tables {
+            sqlx::query(&format!("DROP TABLE \"{table}\""))
+                .execute(self.pool())
+                .await?;
+        }
+
+        schema::migrate(self.pool(), &MIGRATIONS).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteClient {
+    async fn authorize_device(&self, mac: &Mac) -> StorageResult<Option<NodeId>> {
+        Ok(sqlx::query(include_str!(
+            "../../../queries/sqlite/get_device_by_mac.sql"
+        ))
+        .bind(mac.to_string())
+        .fetch_optional(self.pool())
+        .await?
+        .map(|row| row.try_get(0))
+        .transpose()?)
+    }
+
+    async fn authorize_relayed_device(
+        &self,
+        mac: &Mac,
+        gateway: NodeId,
+    ) -> StorageResult<Option<NodeId>> {
+        Ok(sqlx::query(include_str!(
+            "../../../queries/sqlite/get_relayed_device_by_mac.sql"
+        ))
+        .bind(mac.to_string())
+        .bind(gateway)
+        .fetch_optional(self.pool())
+        .await?
+        .map(|row| row.try_get(0))
+        .transpose()?)
+    }
+
+    async fn post_results(
+        &self,
+        node: NodeId,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<MeasurementId> {
+        self.insert_measurement(
+            include_str!("../../../queries/sqlite/post_results.sql"),
+            node,
+            None,
+            temp,
+            hum,
+            air_p,
+        )
+        .await?
+        .ok_or_else(|| StorageError::Constraint("measurement was not stored".into()))
+    }
+
+    async fn post_queued_results(
+        &self,
+        node: NodeId,
+        measured_at: i64,
+        temp: Temperature,
+        hum: Humidity,
+        air_p: Option<AirPressure>,
+    ) -> StorageResult<Option<MeasurementId>> {
+        self.insert_measurement(
+            include_str!("../../../queries/sqlite/post_queued_results.sql"),
+            node,
+            Some(measured_at),
+            temp,
+            hum,
+            air_p,
+        )
+        .await
+    }
+
+    async fn post_stats(
+        &self,
+        measurement: MeasurementId,
+        battery: &BatteryVoltage,
+        wifi_ssid: &str,
+        wifi_rssi: Rssi,
+    ) -> StorageResult<()> {
+        sqlx::query(include_str!("../../../queries/sqlite/post_stats.sql"))
+            .bind(measurement)
+            .bind(to_real(*battery)?)
+            .bind(wifi_ssid)
+            .bind(wifi_rssi)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_location(
+        &self,
+        id: NodeId,
+        latitude: f64,
+        longitude: f64,
+        altitude: Option<Altitude>,
+    ) -> StorageResult<()> {
+        sqlx::query(include_str!("../../../queries/sqlite/set_location.sql"))
+            .bind(id)
+            .bind(latitude)
+            .bind(longitude)
+            .bind(altitude)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn nodes_within_radius(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> StorageResult<Vec<NodeLocation>> {
+        let mut nodes = self.located_nodes().await?;
+
+        for node in &mut nodes {
+            node.distance = Some(distance_km(
+                (latitude, longitude),
+                (node.latitude, node.longitude),
+            ));
+        }
+
+        nodes.retain(|node| node.distance.is_some_and(|distance| distance <= radius_km));
+        nodes.sort_by(|a, b| {
+            a.distance
+                .unwrap_or_default()
+                .total_cmp(&b.distance.unwrap_or_default())
+        });
+
+        Ok(nodes)
+    }
+
+    async fn nodes_within_box(
+        &self,
+        (min_latitude, min_longitude): (f64, f64),
+        (max_latitude, max_longitude): (f64, f64),
+    ) -> StorageResult<Vec<NodeLocation>> {
+        let mut nodes = self.located_nodes().await?;
+
+        nodes.retain(|node| {
+            (min_latitude..=max_latitude).contains(&node.latitude)
+                && (min_longitude..=max_longitude).contains(&node.longitude)
+        });
+
+        Ok(nodes)
+    }
+
+    async fn create_notification(
+        &self,
+        id: NodeId,
+        content: &str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+        expiry: Option<i32>,
+        deduplicate: bool,
+    ) -> StorageResult<()> {
+        let mut tx = self.pool().begin().await?;
+
+        if deduplicate {
+            let updated = sqlx::query(include_str!(
+                "../../../queries/sqlite/deduplicate_notification.sql"
+            ))
+            .bind(id)
+            .bind(severity.name())
+            .bind(category.name())
+            .bind(content)
+            .bind(expiry)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            if updated > 0 {
+                tx.commit().await?;
+                return Ok(());
+            }
+        }
+
+        sqlx::query(include_str!(
+            "../../../queries/sqlite/create_notification.sql"
+        ))
+        .bind(id)
+        .bind(severity.name())
+        .bind(category.name())
+        .bind(content)
+        .bind(expiry)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_notifications(
+        &self,
+        unread_only: bool,
+    ) -> StorageResult<Vec<StoredNotification>> {
+        let rows = sqlx::query(include_str!(
+            "../../../queries/sqlite/list_notifications.sql"
+        ))
+        .bind(unread_only)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(stored_notification)
+            .collect::<sqlx::Result<_>>()?)
+    }
+
+    async fn mark_notification_read(&self, id: i32) -> StorageResult<bool> {
+        Ok(sqlx::query(include_str!(
+            "../../../queries/sqlite/mark_notification_read.sql"
+        ))
+        .bind(id)
+        .execute(self.pool())
+        .await?
+        .rows_affected()
+            > 0)
+    }
+
+    async fn purge_notifications(&self) -> StorageResult<u64> {
+        Ok(sqlx::query(include_str!(
+            "../../../queries/sqlite/purge_notifications.sql"
+        ))
+        .execute(self.pool())
+        .await?
+        .rows_affected())
+    }
+
+    async fn get_settings(
+        &self,
+        id: NodeId,
+        settings: &[SettingName],
+    ) -> StorageResult<Vec<Option<SettingValue>>> {
+        let mut results = vec![None; settings.len()];
+
+        if settings.is_empty() {
+            return Ok(results);
+        }
+
+        let columns = settings
+            .iter()
+            .map(|setting| setting.name())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        let query = format!("SELECT {columns} FROM settings WHERE node = ?1");
+
+        let Some(row) = sqlx::query(&query)
+            .bind(id)
+            .fetch_optional(self.pool())
+            .await?
+        else {
+            return Ok(results);
+        };
+
+        for (i, setting) in settings.iter().enumerate() {
+            let value: SettingValue = match setting {
+                SettingName::BatteryIgnore
+                | SettingName::Ota
+                | SettingName::Sbop
+                | SettingName::MuteNotifications => row.try_get::<bool, _>(i)?.into(),
+                SettingName::SleepTime => row.try_get::<u16, _>(i)?.into(),
+            };
+
+            results[i] = Some(value);
+        }
+
+        Ok(results)
+    }
+}
+
+/// Decimals are stored as `REAL`s, since SQLite has no exact decimal type.
+fn to_real(value: Decimal) -> StorageResult<f64> {
+    f64::try_from(value)
+        .map_err(|_| StorageError::Constraint(format!("{value} is not representable").into()))
+}
+
+fn node_location(row: &SqliteRow) -> sqlx::Result<NodeLocation> {
+    Ok(NodeLocation {
+        id: row.try_get("id")?,
+        mac_address: row.try_get("mac_address")?,
+        note: row.try_get("note")?,
+        latitude: row.try_get("latitude")?,
+        longitude: row.try_get("longitude")?,
+        altitude: row.try_get("altitude")?,
+        distance: None,
+        when: row.try_get("when")?,
+        temperature: row
+            .try_get::<Option<f64>, _>("temperature")?
+            .and_then(|value| Decimal::try_from(value).ok())
+            .map(|value| value.round_dp(2)),
+        humidity: row.try_get("humidity")?,
+        air_pressure: row.try_get("air_pressure")?,
+    })
+}
+
+fn stored_notification(row: &SqliteRow) -> sqlx::Result<StoredNotification> {
+    Ok(StoredNotification {
+        id: row.try_get("id")?,
+        mac_address: row.try_get("mac_address")?,
+        when: row.try_get("when")?,
+        last_seen: row.try_get("last_seen")?,
+        severity: row.try_get("severity")?,
+        category: row.try_get("category")?,
+        content: row.try_get("content")?,
+        occurrences: row.try_get("occurrences")?,
+        read: row.try_get("read")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqliteClient;
+    use crate::server::{
+        config::Config,
+        storage::{schema::Schema, Storage, StorageError},
+    };
+    use pwmp_types::{
+        dec,
+        mac::Mac,
+        multitype::SettingValue,
+        notification::{NotificationCategory, NotificationSeverity},
+        setting::SettingName,
+        Decimal,
+    };
+    use std::{env, fs, path::PathBuf, process};
+
+    const MAC: Mac = Mac::new(1, 2, 3, 4, 5, 6);
+
+    struct TestDb(SqliteClient, PathBuf);
+
+    impl Drop for TestDb {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = fs::remove_file(format!("{}{suffix}", self.1.display()));
+            }
+        }
+    }
+
+    async fn open(name: &str) -> TestDb {
+        let path = env::temp_dir().join(format!("pwmp-test-{}-{name}.db", process::id()));
+        let mut config = Config::default();
+        config.database.path.clone_from(&path);
+
+        let db = SqliteClient::connect(&config).await.unwrap();
+        db.migrate().await.unwrap();
+        sqlx::query("INSERT INTO devices(mac_address) VALUES (?1)")
+            .bind(MAC.to_string())
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        TestDb(db, path)
+    }
+
+    #[tokio::test]
+    async fn measurements() {
+        let TestDb(db, _) = &open("measurements").await;
+
+        let node = db.authorize_device(&MAC).await.unwrap().unwrap();
+        let measurement = db
+            .post_results(node, dec!(21.50), 45, Some(1013))
+            .await
+            .unwrap();
+        db.post_stats(measurement, &dec!(3.81), "Weather", -67)
+            .await
+            .unwrap();
+        db.set_location(node, 46.0569, 14.5058, Some(295))
+            .await
+            .unwrap();
+
+        let nodes = db.nodes_within_radius(46.05, 14.5, 10.0).await.unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].temperature, Some(dec!(21.50)));
+        assert_eq!(nodes[0].air_pressure, Some(1013));
+        assert!(db
+            .nodes_within_radius(0.0, 0.0, 10.0)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.authorize_device(&Mac::new(6, 5, 4, 3, 2, 1))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_queued_measurement() {
+        let TestDb(db, _) = &open("queued").await;
+        let node = db.authorize_device(&MAC).await.unwrap().unwrap();
+
+        sqlx::query("INSERT INTO devices(mac_address) VALUES ('06:05:04:03:02:01')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let other = db
+            .authorize_device(&Mac::new(6, 5, 4, 3, 2, 1))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let first = db
+            .post_queued_results(node, 1_700_000_000, dec!(12.25), 80, None)
+            .await
+            .unwrap();
+        let duplicate = db
+            .post_queued_results(node, 1_700_000_000, dec!(12.25), 80, None)
+            .await
+            .unwrap();
+        let other = db
+            .post_queued_results(other, 1_700_000_000, dec!(12.25), 80, None)
+            .await
+            .unwrap();
+
+        assert!(first.is_some());
+        assert_eq!(duplicate, None);
+        assert!(other.is_some());
+    }
+
+    #[tokio::test]
+    async fn settings() {
+        let TestDb(db, _) = &open("settings").await;
+        let node = db.authorize_device(&MAC).await.unwrap().unwrap();
+        let names = [SettingName::SleepTime, SettingName::MuteNotifications];
+
+        assert_eq!(db.get_settings(node, &names).await.unwrap(), [None, None]);
+
+        sqlx::query("INSERT INTO settings(node, sleep_time) VALUES (?1, 300)")
+            .bind(node)
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.get_settings(node, &names).await.unwrap(),
+            [
+                Some(SettingValue::Number(300)),
+                Some(SettingValue::Boolean(false))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn notifications() {
+        let TestDb(db, _) = &open("notifications").await;
+        let node = db.authorize_device(&MAC).await.unwrap().unwrap();
+
+        for _ in 0..2 {
+            db.create_notification(
+                node,
+                "Battery low",
+                NotificationSeverity::Warning,
+                NotificationCategory::Battery,
+                Some(3600),
+                true,
+            )
+            .await
+            .unwrap();
+        }
+
+        let notifications = db.list_notifications(true).await.unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].occurrences, 2);
+        assert_eq!(notifications[0].severity, "warning");
+
+        assert!(db
+            .mark_notification_read(notifications[0].id)
+            .await
+            .unwrap());
+        assert!(!db
+            .mark_notification_read(notifications[0].id)
+            .await
+            .unwrap());
+        assert!(db.list_notifications(true).await.unwrap().is_empty());
+        assert_eq!(db.purge_notifications().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn schema() {
+        let TestDb(db, path) = &open("schema").await;
+        assert!(db.schema_status().await.unwrap().check_current().is_ok());
+
+        db.reset().await.unwrap();
+        assert_eq!(db.authorize_device(&MAC).await.unwrap(), None);
+        assert!(db.schema_status().await.unwrap().check_current().is_ok());
+
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations(version, description, success, checksum, execution_time)
+             VALUES (9999, 'future', TRUE, x'00', 0)",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let mut config = Config::default();
+        config.database.path.clone_from(path);
+        assert!(matches!(
+            SqliteClient::new(&config).await,
+            Err(StorageError::Schema(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn schema_status_read_only() {
+        let path = env::temp_dir().join(format!("pwmp-test-{}-status.db", process::id()));
+        let mut config = Config::default();
+        config.database.path.clone_from(&path);
+        let TestDb(db, _) = &TestDb(SqliteClient::connect(&config).await.unwrap(), path);
+
+        let status = db.schema_status().await.unwrap();
+        assert_eq!(status.version(), None);
+        assert_eq!(status.pending().count(), status.migrations.len());
+
+        let tables: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+}
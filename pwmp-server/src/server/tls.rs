@@ -0,0 +1,186 @@
+use super::config::TlsConfig;
+use rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore,
+};
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use x509_parser::{certificate::X509Certificate, prelude::FromDer};
+
+/// Connection to a node, which is encrypted if TLS is enabled.
+#[derive(Debug)]
+pub enum Stream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Failed to read {0}: {1}")]
+    Pem(Box<str>, rustls::pki_types::pem::Error),
+    #[error("No certificates found in {0}")]
+    NoCertificates(Box<str>),
+    #[error("{0}")]
+    Rustls(#[from] rustls::Error),
+    #[error("Invalid client CA: {0}")]
+    ClientCa(#[from] rustls::server::VerifierBuilderError),
+}
+
+impl Stream {
+    /// Wrap an accepted connection, performing the TLS handshake if TLS is enabled.
+    pub async fn accept(socket: TcpStream, tls: Option<&TlsAcceptor>) -> io::Result<Self> {
+        let Some(tls) = tls else {
+            return Ok(Self::Plain(socket));
+        };
+
+        Ok(Self::Tls(Box::new(tls.accept(socket).await?)))
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket().peer_addr()
+    }
+
+    /// Returns the certificate the node authenticated with, if client certificates are required.
+    pub fn peer_certificate(&self) -> Option<&CertificateDer<'static>> {
+        match self {
+            Self::Plain(_) => None,
+            Self::Tls(stream) => stream.get_ref().1.peer_certificates()?.first(),
+        }
+    }
+
+    /// Send a TLS `close_notify`, if TLS is enabled, and close the connection.
+    pub async fn shutdown(&mut self) {
+        let _ = AsyncWriteExt::shutdown(self).await;
+    }
+
+    fn socket(&self) -> &TcpStream {
+        match self {
+            Self::Plain(socket) => socket,
+            Self::Tls(stream) => stream.get_ref().0,
+        }
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(socket) => Pin::new(socket).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Load the certificate and key of the server. If a client CA is configured, nodes must
+/// present a certificate signed by it.
+pub fn load_config(config: &TlsConfig) -> Result<Arc<rustls::ServerConfig>, TlsError> {
+    let provider = Arc::new(ring::default_provider());
+    let chain = load_certs(&config.cert.to_string_lossy())?;
+    let key = PrivateKeyDer::from_pem_file(&config.key)
+        .map_err(|why| TlsError::Pem(config.key.to_string_lossy().into(), why))?;
+
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+
+    let builder = match &config.client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(&path.to_string_lossy())? {
+                roots.add(cert)?;
+            }
+
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider).build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    Ok(Arc::new(builder.with_single_cert(chain, key)?))
+}
+
+/// Returns the common name in the subject of a DER encoded certificate.
+pub fn common_name(cert: &[u8]) -> Option<&str> {
+    let (_, cert) = X509Certificate::from_der(cert).ok()?;
+    let name = cert.tbs_certificate.subject.iter_common_name().next()?;
+
+    name.as_str().ok()
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|why| TlsError::Pem(path.into(), why))?;
+
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(path.into()));
+    }
+
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::common_name;
+    use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+
+    #[test]
+    fn certificate_common_name() {
+        let mut params = CertificateParams::default();
+        params.distinguished_name = DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(DnType::OrganizationName, "PixelWeather");
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "01:02:03:04:05:06");
+
+        let key = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+
+        assert_eq!(common_name(cert.der()), Some("01:02:03:04:05:06"));
+        assert_eq!(common_name(&cert.der()[..40]), None);
+    }
+}
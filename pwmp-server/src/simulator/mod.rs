@@ -0,0 +1,276 @@
+use crate::cli::SimulateArgs;
+use log::{debug, error, info};
+use pwmp_client::{
+    error::Error,
+    report::SessionReport,
+    settings::NodeSettings,
+    tls::{
+        pki_types::{pem::PemObject, CertificateDer},
+        TlsOptions,
+    },
+    transport::Transport,
+    PwmpClient,
+};
+use pwmp_types::mac::Mac;
+use std::{
+    collections::BTreeMap,
+    process::exit,
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use weather::Weather;
+
+mod weather;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How a virtual node connects to the server.
+#[derive(Clone)]
+struct Target {
+    addr: String,
+    pin: Option<CertificateDer<'static>>,
+}
+
+/// Simulation parameters shared by all virtual nodes.
+struct Clock {
+    started: Instant,
+    started_unix: f64,
+    deadline: Instant,
+    time_scale: f64,
+}
+
+/// What the virtual nodes observed.
+#[derive(Default)]
+struct Stats {
+    sessions: usize,
+    accepted: usize,
+    rejected: usize,
+    failed: usize,
+    errors: BTreeMap<String, usize>,
+    /// Time to connect and greet the server, per session.
+    connect: Vec<Duration>,
+    /// Round-trip times of requests that were answered.
+    requests: Vec<Duration>,
+    /// Duration of whole sessions.
+    sessions_time: Vec<Duration>,
+}
+
+/// Spawn a fleet of virtual nodes which periodically post synthetic measurements, and
+/// report how the server handled them.
+#[allow(clippy::needless_pass_by_value)]
+pub fn simulate_main(args: SimulateArgs) {
+    let Ok(first_mac) = Mac::from_str(&args.first_mac) else {
+        error!("Invalid MAC address format");
+        exit(1);
+    };
+
+    if !(args.time_scale.is_finite() && args.time_scale > 0.0) {
+        error!("Invalid time scale {}", args.time_scale);
+        exit(1);
+    }
+
+    let pin = args.pin.as_deref().map(|path| {
+        CertificateDer::from_pem_file(path).unwrap_or_else(|why| {
+            error!("Failed to read {}: {why}", path.display());
+            exit(1);
+        })
+    });
+
+    let target = Target {
+        addr: format!("{}:{}", args.host, args.port.unwrap_or(55300)),
+        pin,
+    };
+
+    let started = Instant::now();
+    let clock = Arc::new(Clock {
+        started,
+        started_unix: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_secs_f64()),
+        deadline: started + Duration::from_secs(args.duration),
+        time_scale: args.time_scale,
+    });
+    let stats = Arc::new(Mutex::new(Stats::default()));
+
+    info!(
+        "Simulating {} nodes starting at {first_mac} for {}s",
+        args.nodes, args.duration
+    );
+
+    let nodes: Vec<_> = (0..args.nodes)
+        .map(|i| {
+            let mac = nth_mac(&first_mac, i);
+            let weather = Weather::new(args.seed.wrapping_add(i as u64));
+            let wake = started + Duration::from_secs(args.stagger).mul_f64(fraction(i, args.nodes));
+            let target = target.clone();
+            let clock = clock.clone();
+            let stats = stats.clone();
+
+            thread::spawn(move || run_node(&mac, weather, wake, &target, &clock, &stats))
+        })
+        .collect();
+
+    while Instant::now() < clock.deadline {
+        thread::sleep(
+            PROGRESS_INTERVAL.min(clock.deadline.saturating_duration_since(Instant::now())),
+        );
+
+        let stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+        info!(
+            "{} sessions so far ({} accepted, {} rejected, {} failed)",
+            stats.sessions, stats.accepted, stats.rejected, stats.failed
+        );
+    }
+
+    for node in nodes {
+        let _ = node.join();
+    }
+
+    let mut stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+    stats.print(started.elapsed());
+}
+
+/// Run a virtual node until the simulation ends. The node wakes up, posts it's measurements
+/// and sleeps for the time the server tells it to.
+fn run_node(
+    mac: &Mac,
+    mut weather: Weather,
+    mut wake: Instant,
+    target: &Target,
+    clock: &Clock,
+    stats: &Mutex<Stats>,
+) {
+    let mut sleep_time = NodeSettings::default().sleep_time;
+
+    while wake < clock.deadline {
+        thread::sleep(wake.saturating_duration_since(Instant::now()));
+
+        let simulated = clock.started.elapsed().mul_f64(clock.time_scale);
+        let sample = weather.sample(
+            clock.started_unix + simulated.as_secs_f64(),
+            simulated.as_secs_f64() / 3600.0,
+        );
+
+        let builder = PwmpClient::builder(mac.clone());
+        let result = match &target.pin {
+            Some(cert) => builder
+                .build_tls(target.addr.as_str(), TlsOptions::pinned(cert.clone()))
+                .and_then(|client| run_session(client, &sample)),
+            None => builder
+                .build(target.addr.as_str())
+                .and_then(|client| run_session(client, &sample)),
+        };
+
+        let mut stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+        match result {
+            Ok((settings, report)) => {
+                sleep_time = settings.sleep_time;
+                stats.record(&report);
+            }
+            Err(why) => {
+                debug!("{mac}: {why}");
+                stats.record_error(&why);
+            }
+        }
+        drop(stats);
+
+        wake = Instant::now() + sleep_time.div_f64(clock.time_scale);
+    }
+}
+
+fn run_session<T: Transport>(
+    mut client: PwmpClient<T>,
+    sample: &weather::Sample,
+) -> Result<(NodeSettings, SessionReport), Error> {
+    let settings = client.fetch_node_settings()?;
+
+    client.post_measurements(sample.temperature, sample.humidity, sample.air_pressure)?;
+    client.post_stats(sample.battery, "simulator", sample.wifi_rssi)?;
+
+    let (result, report) = client.close_with_report();
+    result.map(|()| (settings, report))
+}
+
+impl Stats {
+    fn record(&mut self, report: &SessionReport) {
+        self.sessions += 1;
+        self.accepted += 1;
+        self.connect
+            .push(report.connect_time + report.handshake_time);
+        self.requests.extend(
+            report
+                .requests
+                .iter()
+                .filter(|timing| timing.responded)
+                .map(|timing| timing.rtt),
+        );
+        self.sessions_time.push(report.total_time());
+    }
+
+    fn record_error(&mut self, error: &Error) {
+        self.sessions += 1;
+
+        if matches!(error, Error::Rejected) {
+            self.rejected += 1;
+        } else {
+            self.failed += 1;
+        }
+
+        *self.errors.entry(error.to_string()).or_default() += 1;
+    }
+
+    fn print(&mut self, elapsed: Duration) {
+        info!(
+            "{} sessions in {:.1}s: {} accepted, {} rejected, {} failed",
+            self.sessions,
+            elapsed.as_secs_f64(),
+            self.accepted,
+            self.rejected,
+            self.failed
+        );
+
+        for (error, count) in &self.errors {
+            info!("  {count}x {error}");
+        }
+
+        print_latency("Connect and greeting", &mut self.connect);
+        print_latency("Request round-trip", &mut self.requests);
+        print_latency("Session", &mut self.sessions_time);
+    }
+}
+
+fn print_latency(name: &str, samples: &mut [Duration]) {
+    if samples.is_empty() {
+        return;
+    }
+
+    samples.sort_unstable();
+    info!(
+        "{name}: p50 {:?}, p95 {:?}, p99 {:?}, max {:?}",
+        percentile(samples, 50),
+        percentile(samples, 95),
+        percentile(samples, 99),
+        samples[samples.len() - 1]
+    );
+}
+
+/// Returns the percentile of sorted samples.
+fn percentile(sorted: &[Duration], percentile: usize) -> Duration {
+    sorted[(sorted.len() * percentile / 100).min(sorted.len() - 1)]
+}
+
+/// Returns the MAC address `n` addresses after `first`.
+fn nth_mac(first: &Mac, n: usize) -> Mac {
+    let value = (0..6).fold(0u64, |value, i| value << 8 | u64::from(first[i]));
+    let value = value.wrapping_add(n as u64);
+    let octet = |i: usize| (value >> (8 * (5 - i))) as u8;
+
+    Mac::new(octet(0), octet(1), octet(2), octet(3), octet(4), octet(5))
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn fraction(i: usize, n: usize) -> f64 {
+    i as f64 / n as f64
+}
@@ -0,0 +1,97 @@
+use pwmp_types::{
+    aliases::{AirPressure, BatteryVoltage, Humidity, Rssi, Temperature},
+    Decimal,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::f64::consts::TAU;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+/// Hour of the day (UTC) with the highest temperature.
+const WARMEST_HOUR: f64 = 15.0;
+/// Period of the simulated pressure systems passing by.
+const PRESSURE_PERIOD_DAYS: f64 = 3.0;
+const FULL_BATTERY: f64 = 4.15;
+const EMPTY_BATTERY: f64 = 3.3;
+
+/// Synthetic weather and battery state of a virtual node. Every node gets it's own
+/// climate, sensors and battery, derived from a seed.
+pub struct Weather {
+    rng: StdRng,
+    mean_temperature: f64,
+    temperature_amplitude: f64,
+    mean_humidity: f64,
+    /// Phase of the pressure curve, or `None` if the node has no pressure sensor.
+    pressure_phase: Option<f64>,
+    /// Battery discharge in volts per hour.
+    discharge: f64,
+    mean_rssi: f64,
+}
+
+/// Values measured by a virtual node.
+pub struct Sample {
+    pub temperature: Temperature,
+    pub humidity: Humidity,
+    pub air_pressure: Option<AirPressure>,
+    pub battery: BatteryVoltage,
+    pub wifi_rssi: Rssi,
+}
+
+impl Weather {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        Self {
+            mean_temperature: rng.gen_range(6.0..18.0),
+            temperature_amplitude: rng.gen_range(3.0..8.0),
+            mean_humidity: rng.gen_range(55.0..75.0),
+            pressure_phase: rng.gen_bool(0.7).then(|| rng.gen_range(0.0..TAU)),
+            discharge: rng.gen_range(0.002..0.006),
+            mean_rssi: rng.gen_range(-85.0..-45.0),
+            rng,
+        }
+    }
+
+    /// Take a sample at the specified UNIX time (in seconds), after the node has been
+    /// running for the specified amount of hours.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn sample(&mut self, time: f64, running_hours: f64) -> Sample {
+        let hour = (time % SECONDS_PER_DAY) / 3600.0;
+        let daily = (TAU * (hour - WARMEST_HOUR) / 24.0).cos();
+
+        let temperature = self
+            .temperature_amplitude
+            .mul_add(daily, self.mean_temperature)
+            + self.rng.gen_range(-0.3..0.3);
+
+        // Relative humidity drops as the air warms up.
+        let humidity = 2.5f64.mul_add(
+            self.mean_temperature - temperature,
+            self.mean_humidity + self.rng.gen_range(-2.0..2.0),
+        );
+
+        let air_pressure = self.pressure_phase.map(|phase| {
+            let cycle = TAU * time / (SECONDS_PER_DAY * PRESSURE_PERIOD_DAYS) + phase;
+            6.0f64.mul_add(cycle.sin(), 1013.0) + self.rng.gen_range(-0.5..0.5)
+        });
+
+        let battery = self
+            .discharge
+            .mul_add(-running_hours, FULL_BATTERY)
+            .max(EMPTY_BATTERY)
+            + self.rng.gen_range(-0.01..0.01);
+
+        let wifi_rssi = self.mean_rssi + self.rng.gen_range(-3.0..3.0);
+
+        Sample {
+            temperature: to_decimal(temperature),
+            humidity: humidity.clamp(5.0, 100.0).round() as Humidity,
+            air_pressure: air_pressure.map(|pressure| pressure.round() as AirPressure),
+            battery: to_decimal(battery),
+            wifi_rssi: wifi_rssi.clamp(-100.0, -30.0).round() as Rssi,
+        }
+    }
+}
+
+fn to_decimal(value: f64) -> Decimal {
+    Decimal::try_from(value).unwrap_or_default().round_dp(2)
+}
@@ -1,21 +1,87 @@
 use log::{error, info};
-use pwmp_client::PwmpClient;
-use pwmp_types::mac::Mac;
-use std::str::FromStr;
+use pwmp_client::{
+    tls::{
+        pki_types::{pem::PemObject, CertificateDer},
+        TlsOptions,
+    },
+    PwmpClient,
+};
+use pwmp_types::{discovery::PROTOCOL_VERSION, mac::Mac};
+use std::{path::Path, str::FromStr, time::Duration};
+
+/// Host name which makes the tester discover a server instead.
+const AUTO_HOST: &str = "auto";
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// Try to connect to a server and authenticate with the given MAC address to
-/// check if the server is working properly.
+/// check if the server is working properly. If a pinned certificate is specified,
+/// the connection is made over TLS.
 #[allow(clippy::needless_pass_by_value)]
-pub fn test(host: String, port: Option<u16>, raw_mac: String) {
+pub fn test(host: String, port: Option<u16>, raw_mac: String, pin: Option<&Path>) {
     let Ok(mac) = Mac::from_str(&raw_mac) else {
         error!("Invalid MAC address format");
         return;
     };
 
-    let full_addr = format!("{}:{}", host, port.unwrap_or(55300));
+    let full_addr = if host == AUTO_HOST {
+        let Some(addr) = discover(pin.is_some()) else {
+            return;
+        };
+
+        addr
+    } else {
+        format!("{}:{}", host, port.unwrap_or(55300))
+    };
 
-    match PwmpClient::new(full_addr, mac) {
-        Ok(_) => info!("Client connected successfully!"),
+    let result = match pin {
+        Some(path) => match CertificateDer::from_pem_file(path) {
+            Ok(cert) => PwmpClient::builder(mac)
+                .build_tls(full_addr, TlsOptions::pinned(cert))
+                .map(drop),
+            Err(why) => {
+                error!("Failed to read {}: {why}", path.display());
+                return;
+            }
+        },
+        None => PwmpClient::new(full_addr, mac).map(drop),
+    };
+
+    match result {
+        Ok(()) => info!("Client connected successfully!"),
         Err(why) => error!("Failed to test connection: {why}"),
     };
 }
+
+/// Find a compatible server on the local network and return it's address.
+fn discover(tls: bool) -> Option<String> {
+    info!("Looking for servers on the local network");
+
+    let servers = match PwmpClient::discover(DISCOVERY_TIMEOUT) {
+        Ok(servers) => servers,
+        Err(why) => {
+            error!("Failed to discover servers: {why}");
+            return None;
+        }
+    };
+
+    for server in &servers {
+        info!(
+            "Found server at {} (host {}, protocol v{}, TLS {})",
+            server.addr,
+            server.host.as_deref().unwrap_or("unknown"),
+            server.version,
+            if server.tls { "required" } else { "disabled" }
+        );
+    }
+
+    let Some(server) = servers
+        .into_iter()
+        .find(|server| server.version == PROTOCOL_VERSION && server.tls == tls)
+    else {
+        error!("No compatible server found");
+        return None;
+    };
+
+    info!("Testing {}", server.addr);
+    Some(server.addr.to_string())
+}
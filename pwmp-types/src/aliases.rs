@@ -6,3 +6,8 @@ pub type AirPressure = u16;
 pub type BatteryVoltage = Decimal;
 pub type MeasurementId = u16;
 pub type Rssi = i8;
+pub type Latitude = Decimal;
+pub type Longitude = Decimal;
+pub type Altitude = i16;
+pub type SessionToken = u128;
+pub type Timestamp = u64;
@@ -0,0 +1,401 @@
+use crate::{
+    aliases::{
+        AirPressure, Altitude, BatteryVoltage, Humidity, Latitude, Longitude, Rssi, SessionToken,
+        Temperature, Timestamp,
+    },
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity},
+    request::Request,
+    response::Response,
+    setting::SettingName,
+    Message, ENCODING,
+};
+use alloc::boxed::Box;
+use core::{fmt::Debug, marker::PhantomData};
+use serde::Deserialize;
+
+/// Borrowed counterpart of [`Message`].
+///
+/// Strings and lists are not copied out of the buffer the message was decoded from,
+/// which makes decoding allocation-free, with the exception of relayed requests. Use
+/// [`into_owned()`](Self::into_owned) to convert it to a [`Message`] when needed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MessageRef<'a> {
+    /// See [`Message::Request`].
+    Request(RequestRef<'a>),
+    /// See [`Message::Response`].
+    Response(ResponseRef<'a>),
+}
+
+/// Borrowed counterpart of [`Request`]. See it's variants for documentation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[allow(missing_docs)]
+pub enum RequestRef<'a> {
+    Ping,
+    Bye,
+    Hello {
+        mac: Mac,
+    },
+    PostResults {
+        temperature: Temperature,
+        humidity: Humidity,
+        air_pressure: Option<AirPressure>,
+    },
+    PostStats {
+        battery: BatteryVoltage,
+        wifi_ssid: &'a str,
+        wifi_rssi: Rssi,
+    },
+    SendNotification {
+        content: &'a str,
+        severity: NotificationSeverity,
+        category: NotificationCategory,
+    },
+    GetSettings(SeqRef<'a, SettingName>),
+    PostLocation {
+        latitude: Latitude,
+        longitude: Longitude,
+        altitude: Option<Altitude>,
+    },
+    Resume {
+        mac: Mac,
+        session: SessionToken,
+    },
+    Relay {
+        child: Mac,
+        request: Box<RequestRef<'a>>,
+    },
+    PostQueued {
+        measured_at: Timestamp,
+        temperature: Temperature,
+        humidity: Humidity,
+        air_pressure: Option<AirPressure>,
+        battery: Option<BatteryVoltage>,
+        wifi_ssid: Option<&'a str>,
+        wifi_rssi: Option<Rssi>,
+    },
+}
+
+/// Borrowed counterpart of [`Response`]. See it's variants for documentation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[allow(missing_docs)]
+pub enum ResponseRef<'a> {
+    Pong,
+    Ok,
+    Reject,
+    Settings(SeqRef<'a, SettingValue>),
+    Welcome {
+        session: SessionToken,
+        resumed: bool,
+    },
+    Postponed,
+}
+
+/// A list of items that are decoded lazily from the underlying buffer.
+///
+/// The contents are validated when the containing message is decoded, so iterating
+/// over the items cannot fail.
+pub struct SeqRef<'a, T> {
+    len: usize,
+    raw: &'a [u8],
+    _item: PhantomData<T>,
+}
+
+/// Sequential reader over a serialized message.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> MessageRef<'a> {
+    /// Deserialize a message from raw bytes, borrowing from them where possible.
+    #[must_use]
+    pub fn from_raw(bytes: &'a [u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+
+        match reader.read::<u32>()? {
+            0 => RequestRef::read(&mut reader, false).map(Self::Request),
+            1 => ResponseRef::read(&mut reader).map(Self::Response),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the contained [`RequestRef`].
+    /// If the message contains a [`ResponseRef`] instead, `None` is returned.
+    #[must_use]
+    pub const fn request(&self) -> Option<&RequestRef<'a>> {
+        if let Self::Request(req) = self {
+            Some(req)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the contained [`ResponseRef`].
+    /// If the message contains a [`RequestRef`] instead, `None` is returned.
+    #[must_use]
+    pub const fn response(&self) -> Option<&ResponseRef<'a>> {
+        if let Self::Response(resp) = self {
+            Some(resp)
+        } else {
+            None
+        }
+    }
+
+    /// Similar to [`request()`](Self::request), but consumes the message itself.
+    #[must_use]
+    pub fn to_request(self) -> Option<RequestRef<'a>> {
+        if let Self::Request(req) = self {
+            Some(req)
+        } else {
+            None
+        }
+    }
+
+    /// Similar to [`response()`](Self::response), but consumes the message itself.
+    #[must_use]
+    pub fn to_response(self) -> Option<ResponseRef<'a>> {
+        if let Self::Response(resp) = self {
+            Some(resp)
+        } else {
+            None
+        }
+    }
+
+    /// Convert the message to an owned [`Message`].
+    #[must_use]
+    pub fn into_owned(self) -> Message {
+        match self {
+            Self::Request(req) => Message::Request(req.into_owned()),
+            Self::Response(resp) => Message::Response(resp.into_owned()),
+        }
+    }
+}
+
+impl<'a> RequestRef<'a> {
+    /// Relayed requests cannot be relays themselves, which also bounds the recursion.
+    fn read(reader: &mut Reader<'a>, relayed: bool) -> Option<Self> {
+        let request = match reader.read::<u32>()? {
+            0 => Self::Ping,
+            1 => Self::Bye,
+            2 => Self::Hello {
+                mac: reader.read()?,
+            },
+            3 => Self::PostResults {
+                temperature: reader.read()?,
+                humidity: reader.read()?,
+                air_pressure: reader.read()?,
+            },
+            4 => Self::PostStats {
+                battery: reader.read()?,
+                wifi_ssid: reader.read()?,
+                wifi_rssi: reader.read()?,
+            },
+            5 => Self::SendNotification {
+                content: reader.read()?,
+                severity: reader.read()?,
+                category: reader.read()?,
+            },
+            6 => Self::GetSettings(reader.read_seq()?),
+            7 => Self::PostLocation {
+                latitude: reader.read()?,
+                longitude: reader.read()?,
+                altitude: reader.read()?,
+            },
+            8 => Self::Resume {
+                mac: reader.read()?,
+                session: reader.read()?,
+            },
+            9 if !relayed => Self::Relay {
+                child: reader.read()?,
+                request: Box::new(Self::read(reader, true)?),
+            },
+            10 => Self::PostQueued {
+                measured_at: reader.read()?,
+                temperature: reader.read()?,
+                humidity: reader.read()?,
+                air_pressure: reader.read()?,
+                battery: reader.read()?,
+                wifi_ssid: reader.read()?,
+                wifi_rssi: reader.read()?,
+            },
+            _ => return None,
+        };
+
+        Some(request)
+    }
+
+    /// Convert the request to an owned [`Request`].
+    #[must_use]
+    pub fn into_owned(self) -> Request {
+        match self {
+            Self::Ping => Request::Ping,
+            Self::Bye => Request::Bye,
+            Self::Hello { mac } => Request::Hello { mac },
+            Self::PostResults {
+                temperature,
+                humidity,
+                air_pressure,
+            } => Request::PostResults {
+                temperature,
+                humidity,
+                air_pressure,
+            },
+            Self::PostStats {
+                battery,
+                wifi_ssid,
+                wifi_rssi,
+            } => Request::PostStats {
+                battery,
+                wifi_ssid: wifi_ssid.into(),
+                wifi_rssi,
+            },
+            Self::SendNotification {
+                content,
+                severity,
+                category,
+            } => Request::SendNotification {
+                content: content.into(),
+                severity,
+                category,
+            },
+            Self::GetSettings(settings) => Request::GetSettings(settings.to_boxed()),
+            Self::PostLocation {
+                latitude,
+                longitude,
+                altitude,
+            } => Request::PostLocation {
+                latitude,
+                longitude,
+                altitude,
+            },
+            Self::Resume { mac, session } => Request::Resume { mac, session },
+            Self::Relay { child, request } => Request::Relay {
+                child,
+                request: Box::new(request.into_owned()),
+            },
+            Self::PostQueued {
+                measured_at,
+                temperature,
+                humidity,
+                air_pressure,
+                battery,
+                wifi_ssid,
+                wifi_rssi,
+            } => Request::PostQueued {
+                measured_at,
+                temperature,
+                humidity,
+                air_pressure,
+                battery,
+                wifi_ssid: wifi_ssid.map(Into::into),
+                wifi_rssi,
+            },
+        }
+    }
+}
+
+impl<'a> ResponseRef<'a> {
+    fn read(reader: &mut Reader<'a>) -> Option<Self> {
+        let response = match reader.read::<u32>()? {
+            0 => Self::Pong,
+            1 => Self::Ok,
+            2 => Self::Reject,
+            3 => Self::Settings(reader.read_seq()?),
+            4 => Self::Welcome {
+                session: reader.read()?,
+                resumed: reader.read()?,
+            },
+            5 => Self::Postponed,
+            _ => return None,
+        };
+
+        Some(response)
+    }
+
+    /// Convert the response to an owned [`Response`].
+    #[must_use]
+    pub fn into_owned(self) -> Response {
+        match self {
+            Self::Pong => Response::Pong,
+            Self::Ok => Response::Ok,
+            Self::Reject => Response::Reject,
+            Self::Settings(values) => Response::Settings(values.to_boxed()),
+            Self::Welcome { session, resumed } => Response::Welcome { session, resumed },
+            Self::Postponed => Response::Postponed,
+        }
+    }
+}
+
+impl<'a, T: Deserialize<'a>> SeqRef<'a, T> {
+    /// Returns the number of items.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the list is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator that decodes the items one by one.
+    pub fn iter(&self) -> impl Iterator<Item = T> + 'a {
+        let mut reader = Reader(self.raw);
+
+        (0..self.len).map_while(move |_| reader.read())
+    }
+
+    /// Decode all items into an owned, boxed slice.
+    #[must_use]
+    pub fn to_boxed(&self) -> Box<[T]> {
+        self.iter().collect()
+    }
+}
+
+impl<T> Clone for SeqRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SeqRef<'_, T> {}
+
+impl<T> PartialEq for SeqRef<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.raw == other.raw
+    }
+}
+
+impl<T> Eq for SeqRef<'_, T> {}
+
+impl<'a, T: Deserialize<'a> + Debug> Debug for SeqRef<'a, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> Reader<'a> {
+    /// Read a single value and advance past it.
+    fn read<T: Deserialize<'a>>(&mut self) -> Option<T> {
+        let (value, size) = bincode::serde::borrow_decode_from_slice(self.0, ENCODING).ok()?;
+        self.0 = self.0.get(size..)?;
+
+        Some(value)
+    }
+
+    /// Validate a length-prefixed list and advance past it.
+    fn read_seq<T: Deserialize<'a>>(&mut self) -> Option<SeqRef<'a, T>> {
+        let len = usize::try_from(self.read::<u64>()?).ok()?;
+        let start = self.0;
+
+        for _ in 0..len {
+            self.read::<T>()?;
+        }
+
+        Some(SeqRef {
+            len,
+            raw: &start[..start.len() - self.0.len()],
+            _item: PhantomData,
+        })
+    }
+}
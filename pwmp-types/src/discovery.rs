@@ -0,0 +1,56 @@
+use crate::ENCODING;
+use alloc::{boxed::Box, vec::Vec};
+use core::net::Ipv4Addr;
+use serde::{Deserialize, Serialize};
+
+/// UDP port servers listen on for discovery probes.
+pub const DISCOVERY_PORT: u16 = 55301;
+
+/// Multicast group servers join to receive discovery probes, in the organization-local scope.
+pub const DISCOVERY_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 55, 30);
+
+/// Version of the protocol spoken by servers announcing themselves. Increased on incompatible
+/// changes, so clients can ignore servers they can't talk to.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Prefix of every discovery datagram, so unrelated traffic on the port is ignored.
+const MAGIC: [u8; 4] = *b"PWMP";
+
+/// A datagram used to find servers on the local network. Clients broadcast a
+/// [`Probe`](Self::Probe) and servers answer with an [`Announce`](Self::Announce).
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub enum Discovery {
+    /// Sent by a client looking for servers.
+    Probe,
+    /// Sent by a server in response to a probe.
+    Announce {
+        /// Host name or address to connect to. If not specified, clients should connect to
+        /// the address the announcement came from.
+        host: Option<Box<str>>,
+        /// PWMP port
+        port: u16,
+        /// Protocol version, see [`PROTOCOL_VERSION`].
+        version: u16,
+        /// Whether the server requires TLS.
+        tls: bool,
+    },
+}
+
+impl Discovery {
+    /// Serialize the datagram into raw bytes.
+    #[must_use]
+    pub fn to_raw(&self) -> Vec<u8> {
+        let mut raw = MAGIC.to_vec();
+        raw.extend(bincode::serde::encode_to_vec(self, ENCODING).unwrap());
+        raw
+    }
+
+    /// Deserialize a datagram from raw bytes. Returns `None` if the bytes are not a discovery
+    /// datagram.
+    #[must_use]
+    pub fn from_raw(bytes: &[u8]) -> Option<Self> {
+        bincode::serde::decode_from_slice(bytes.strip_prefix(&MAGIC)?, ENCODING)
+            .ok()
+            .map(|(datagram, _)| datagram)
+    }
+}
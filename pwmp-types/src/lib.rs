@@ -3,6 +3,14 @@
     clippy::missing_panics_doc,
     clippy::cast_possible_truncation
 )]
+#![no_std]
+extern crate alloc;
+
+use alloc::vec::Vec;
+use bincode::{
+    config::{Configuration, Fixint, Limit, LittleEndian},
+    enc::write::SizeWriter,
+};
 use request::Request;
 use response::Response;
 pub use rust_decimal::Decimal;
@@ -11,10 +19,16 @@ use serde::{Deserialize, Serialize};
 
 /// Type aliases
 pub mod aliases;
+/// Contains the borrowed [`MessageRef`](borrowed::MessageRef) type, used for allocation-free decoding
+pub mod borrowed;
+/// Contains the [`Discovery`](discovery::Discovery) datagram used to find servers
+pub mod discovery;
 /// Contains the [`Mac`](mac::Mac) address type
 pub mod mac;
 /// Contains the [`SettingValue`](multitype::SettingValue) type
 pub mod multitype;
+/// Contains the notification [`NotificationSeverity`](notification::NotificationSeverity) and [`NotificationCategory`](notification::NotificationCategory) types
+pub mod notification;
 /// Contains the [`Request`] type and it's implementations
 pub mod request;
 /// Contains the [`Response`] type and it's implementations
@@ -25,6 +39,13 @@ pub mod setting;
 /// Node ID type alias
 pub type NodeId = i16;
 
+/// Encoding of messages, the same as the default one of bincode 1.x.
+///
+/// The limit stops bogus lengths from allocating large amounts of memory while decoding,
+/// valid messages are much smaller.
+pub(crate) const ENCODING: Configuration<LittleEndian, Fixint, Limit<4096>> =
+    bincode::config::legacy().with_limit();
+
 /// A Message object.
 /// Can either be a request or a response.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -39,13 +60,15 @@ impl Message {
     /// Serialize the message into raw bytes.
     #[must_use]
     pub fn to_raw(self) -> Vec<u8> {
-        bincode::serialize(&self).unwrap()
+        bincode::serde::encode_to_vec(&self, ENCODING).unwrap()
     }
 
     /// Deserialize a message from raw bytes.
     #[must_use]
     pub fn from_raw(bytes: &[u8]) -> Option<Self> {
-        bincode::deserialize(bytes).ok()
+        bincode::serde::decode_from_slice(bytes, ENCODING)
+            .ok()
+            .map(|(message, _)| message)
     }
 
     /// Returns a reference to the contained [`Request`].
@@ -101,6 +124,9 @@ impl Message {
     /// ```
     #[must_use]
     pub fn size(&self) -> usize {
-        bincode::serialized_size(self).unwrap() as usize
+        let mut writer = SizeWriter::default();
+        bincode::serde::encode_into_writer(self, &mut writer, ENCODING).unwrap();
+
+        writer.bytes_written
     }
 }
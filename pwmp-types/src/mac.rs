@@ -1,15 +1,15 @@
-use serde::{Deserialize, Serialize};
-use std::{
+use core::{
     fmt::Display,
     num::ParseIntError,
     ops::{Index, IndexMut},
     str::FromStr,
 };
+use serde::{Deserialize, Serialize};
 
 const MAC_STR_LEN: usize = "11:22:33:44:55:66".len();
 
 /// MAC address.
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct Mac(u8, u8, u8, u8, u8, u8);
 
 /// MAC address parse error.
@@ -47,7 +47,7 @@ impl FromStr for Mac {
 }
 
 impl Display for Mac {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of a notification's text content.
+pub const MAX_NOTIFICATION_LEN: usize = 64;
+
+/// Severity of a notification.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum NotificationSeverity {
+    /// Purely informational, no action is required.
+    #[default]
+    Info,
+    /// Something is not right, but the node can still operate.
+    Warning,
+    /// The node cannot operate properly and requires attention.
+    Critical,
+}
+
+/// Category of a notification.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum NotificationCategory {
+    /// Does not fit into any other category.
+    #[default]
+    General,
+    /// Battery status, for eg. low voltage.
+    Battery,
+    /// Sensor failures or out-of-range readings.
+    Sensor,
+    /// Wireless network or server connection issues.
+    Connectivity,
+    /// Firmware and OTA update events.
+    Firmware,
+}
+
+impl NotificationSeverity {
+    /// Convert the severity to it's string representation.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+
+    /// Parse a severity from it's string representation.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(Self::Info),
+            "warning" => Some(Self::Warning),
+            "critical" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl NotificationCategory {
+    /// Convert the category to it's string representation.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::General => "general",
+            Self::Battery => "battery",
+            Self::Sensor => "sensor",
+            Self::Connectivity => "connectivity",
+            Self::Firmware => "firmware",
+        }
+    }
+
+    /// Parse a category from it's string representation.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "general" => Some(Self::General),
+            "battery" => Some(Self::Battery),
+            "sensor" => Some(Self::Sensor),
+            "connectivity" => Some(Self::Connectivity),
+            "firmware" => Some(Self::Firmware),
+            _ => None,
+        }
+    }
+}
@@ -1,11 +1,24 @@
 use crate::{
-    aliases::{AirPressure, BatteryVoltage, Humidity, Rssi, Temperature},
+    aliases::{
+        AirPressure, Altitude, BatteryVoltage, Humidity, Latitude, Longitude, Rssi, SessionToken,
+        Temperature, Timestamp,
+    },
     mac::Mac,
+    notification::{NotificationCategory, NotificationSeverity},
     setting::SettingName,
 };
+use alloc::boxed::Box;
 use serde::{Deserialize, Serialize};
 
+/// Session token sent in [`Request::Resume`] to start a new session.
+///
+/// Servers never issue this token, so it can't refer to an existing session.
+pub const NEW_SESSION: SessionToken = 0;
+
 /// A request message used by nodes to ask the PWMP server to perform an operation.
+///
+/// New variants must be added at the end, since the position of a variant determines it's
+/// encoding. Moving one breaks communication with deployed nodes.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum Request {
     /// Used to check if the server is alive.
@@ -45,8 +58,71 @@ pub enum Request {
     /// Store a notification in the database. These can be read by other applications.
     ///
     /// Notifications are usually used to inform about low battery status.
-    SendNotification(Box<str>),
+    SendNotification {
+        /// Text content of the notification
+        content: Box<str>,
+        /// How serious the reported issue is
+        severity: NotificationSeverity,
+        /// What part of the node the notification relates to
+        category: NotificationCategory,
+    },
 
     /// Retrieve the node's settings from the database.
     GetSettings(Box<[SettingName]>),
+
+    /// Report the node's geographic location, either from a GPS module or a manual configuration.
+    PostLocation {
+        /// Latitude in degrees, from -90 to 90
+        latitude: Latitude,
+        /// Longitude in degrees, from -180 to 180
+        longitude: Longitude,
+        /// Altitude above sea level in meters *(if known)*
+        altitude: Option<Altitude>,
+    },
+
+    /// Resume a previous session after the connection was lost, instead of sending [`Hello`](Self::Hello).
+    ///
+    /// If the session is unknown or has expired, the server will start a new one. Nodes that
+    /// support sessions should send this with [`NEW_SESSION`] instead of `Hello` when they don't
+    /// have a session yet, since a plain `Hello` is answered with `Ok` and its session can't be
+    /// resumed.
+    Resume {
+        #[allow(clippy::doc_markdown)]
+        /// The node's MAC address. This address should be that of the WiFi interface.
+        mac: Mac,
+        /// Token received from the server when the session was started.
+        session: SessionToken,
+    },
+
+    /// Perform a request on behalf of another node. Used by gateways that relay requests of nodes
+    /// which cannot reach the server directly, for eg. LoRa or BLE sensors.
+    ///
+    /// The relayed node must be assigned to the gateway on the server. Relaying `Ping`, `Hello`,
+    /// `Resume`, `Bye` or nested `Relay` requests is not allowed.
+    Relay {
+        /// MAC address of the relayed node.
+        child: Mac,
+        /// The request to perform as the relayed node.
+        request: Box<Request>,
+    },
+
+    /// Post measurement results that could not be delivered when they were taken, for eg. because
+    /// the server was unreachable. Unlike [`PostResults`](Self::PostResults), multiple queued
+    /// results can be posted in a single session.
+    PostQueued {
+        /// When the measurement was taken, as a UNIX timestamp in seconds.
+        measured_at: Timestamp,
+        /// Temperature
+        temperature: Temperature,
+        /// Humidity
+        humidity: Humidity,
+        /// Air pressure *(if supported by the node)*
+        air_pressure: Option<AirPressure>,
+        /// Node's battery voltage at the time of the measurement *(if known)*
+        battery: Option<BatteryVoltage>,
+        /// ESSID of the wireless network at the time of the measurement *(if known)*
+        wifi_ssid: Option<Box<str>>,
+        /// RSSI of the connection to the wireless network at the time of the measurement *(if known)*
+        wifi_rssi: Option<Rssi>,
+    },
 }
@@ -1,4 +1,5 @@
-use crate::multitype::SettingValue;
+use crate::{aliases::SessionToken, multitype::SettingValue};
+use alloc::boxed::Box;
 use serde::{Deserialize, Serialize};
 
 /// A response message used by the PWMP server to respond to [`Request`](crate::request::Request)s.
@@ -15,4 +16,19 @@ pub enum Response {
 
     /// List of setting values requested by [`Request::GetSettings`](crate::request::Request::GetSettings).
     Settings(Box<[SettingValue]>),
+
+    /// The server has authorized the node and started, or resumed a session.
+    /// This is the response to [`Request::Resume`](crate::request::Request::Resume). A plain
+    /// [`Request::Hello`](crate::request::Request::Hello) is answered with [`Ok`](Self::Ok).
+    Welcome {
+        /// Token that can be used to resume the session if the connection is lost.
+        session: SessionToken,
+        /// Whether a previous session was resumed.
+        resumed: bool,
+    },
+
+    /// The request cannot be processed yet and should be sent again in a later session. This is
+    /// used for [`Request::PostQueued`](crate::request::Request::PostQueued) messages with a
+    /// measurement from the future, according to the server's clock.
+    Postponed,
 }
@@ -0,0 +1,160 @@
+use pwmp_types::{
+    borrowed::{MessageRef, RequestRef, ResponseRef},
+    dec,
+    mac::Mac,
+    multitype::SettingValue,
+    notification::{NotificationCategory, NotificationSeverity},
+    request::Request,
+    response::Response,
+    setting::SettingName,
+    Decimal, Message,
+};
+
+fn all_messages() -> Vec<Message> {
+    vec![
+        Message::Request(Request::Ping),
+        Message::Request(Request::Bye),
+        Message::Request(Request::Hello {
+            mac: Mac::new(1, 2, 3, 4, 5, 6),
+        }),
+        Message::Request(Request::PostResults {
+            temperature: dec!(21.50),
+            humidity: 45,
+            air_pressure: Some(1013),
+        }),
+        Message::Request(Request::PostStats {
+            battery: dec!(3.81),
+            wifi_ssid: "Weather".into(),
+            wifi_rssi: -67,
+        }),
+        Message::Request(Request::SendNotification {
+            content: "Battery low".into(),
+            severity: NotificationSeverity::Warning,
+            category: NotificationCategory::Battery,
+        }),
+        Message::Request(Request::PostLocation {
+            latitude: dec!(46.0569),
+            longitude: dec!(-14.5058),
+            altitude: None,
+        }),
+        Message::Request(Request::GetSettings(
+            [SettingName::SleepTime, SettingName::Ota, SettingName::Sbop].into(),
+        )),
+        Message::Request(Request::Resume {
+            mac: Mac::new(1, 2, 3, 4, 5, 6),
+            session: 0x0123_4567_89ab_cdef_0123_4567_89ab_cdef,
+        }),
+        Message::Request(Request::Relay {
+            child: Mac::new(6, 5, 4, 3, 2, 1),
+            request: Box::new(Request::PostStats {
+                battery: dec!(3.70),
+                wifi_ssid: "Gateway".into(),
+                wifi_rssi: -80,
+            }),
+        }),
+        Message::Request(Request::PostQueued {
+            measured_at: 1_700_000_000,
+            temperature: dec!(-3.25),
+            humidity: 80,
+            air_pressure: None,
+            battery: Some(dec!(3.65)),
+            wifi_ssid: Some("Weather".into()),
+            wifi_rssi: Some(-71),
+        }),
+        Message::Request(Request::PostQueued {
+            measured_at: 1_700_000_600,
+            temperature: dec!(-3.50),
+            humidity: 81,
+            air_pressure: Some(1020),
+            battery: None,
+            wifi_ssid: None,
+            wifi_rssi: None,
+        }),
+        Message::Response(Response::Pong),
+        Message::Response(Response::Ok),
+        Message::Response(Response::Reject),
+        Message::Response(Response::Settings(
+            [
+                SettingValue::Number(60),
+                SettingValue::Decimal(dec!(3.30)),
+                SettingValue::Boolean(true),
+            ]
+            .into(),
+        )),
+        Message::Response(Response::Welcome {
+            session: u128::MAX,
+            resumed: true,
+        }),
+        Message::Response(Response::Postponed),
+    ]
+}
+
+#[test]
+fn borrowed_roundtrip() {
+    for message in all_messages() {
+        let raw = message.clone().to_raw();
+        let borrowed = MessageRef::from_raw(&raw).unwrap();
+
+        assert_eq!(borrowed.into_owned(), message);
+    }
+}
+
+#[test]
+fn borrowed_str_points_into_buffer() {
+    let raw = Message::Request(Request::PostStats {
+        battery: dec!(3.81),
+        wifi_ssid: "Weather".into(),
+        wifi_rssi: -67,
+    })
+    .to_raw();
+
+    let Some(RequestRef::PostStats { wifi_ssid, .. }) =
+        MessageRef::from_raw(&raw).unwrap().to_request()
+    else {
+        panic!("Expected PostStats");
+    };
+
+    assert_eq!(wifi_ssid, "Weather");
+    assert!(raw.as_ptr_range().contains(&wifi_ssid.as_ptr()));
+}
+
+#[test]
+fn borrowed_settings_iter() {
+    let raw = Message::Response(Response::Settings(
+        [SettingValue::Number(60), SettingValue::Boolean(false)].into(),
+    ))
+    .to_raw();
+
+    let Some(ResponseRef::Settings(values)) = MessageRef::from_raw(&raw).unwrap().to_response()
+    else {
+        panic!("Expected Settings");
+    };
+
+    assert_eq!(values.len(), 2);
+    assert_eq!(
+        values.iter().collect::<Vec<_>>(),
+        [SettingValue::Number(60), SettingValue::Boolean(false)]
+    );
+}
+
+#[test]
+fn borrowed_truncated() {
+    let raw = Message::Request(Request::GetSettings(
+        [SettingName::SleepTime, SettingName::Ota].into(),
+    ))
+    .to_raw();
+
+    assert!(MessageRef::from_raw(&raw[..raw.len() - 1]).is_none());
+    assert!(MessageRef::from_raw(&[]).is_none());
+}
+
+#[test]
+fn borrowed_nested_relay() {
+    let relay = |request| Request::Relay {
+        child: Mac::new(6, 5, 4, 3, 2, 1),
+        request: Box::new(request),
+    };
+    let raw = Message::Request(relay(relay(Request::Ping))).to_raw();
+
+    assert!(MessageRef::from_raw(&raw).is_none());
+}
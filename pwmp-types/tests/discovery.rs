@@ -0,0 +1,24 @@
+use pwmp_types::{discovery::Discovery, request::Request, Message};
+
+#[test]
+pub fn announce_verify() {
+    let announce = Discovery::Announce {
+        host: Some("pwmp.local".into()),
+        port: 55300,
+        version: 1,
+        tls: true,
+    };
+    let parsed = Discovery::from_raw(&announce.to_raw()).unwrap();
+
+    assert_eq!(announce, parsed);
+}
+
+#[test]
+pub fn reject_foreign() {
+    assert_eq!(Discovery::from_raw(b""), None);
+    assert_eq!(Discovery::from_raw(b"PWM"), None);
+    assert_eq!(
+        Discovery::from_raw(&Message::Request(Request::Ping).to_raw()),
+        None
+    );
+}
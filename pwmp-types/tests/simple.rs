@@ -1,4 +1,11 @@
-use pwmp_types::{request::Request, Message};
+use pwmp_types::{
+    dec,
+    mac::Mac,
+    notification::{NotificationCategory, NotificationSeverity},
+    request::Request,
+    response::Response,
+    Decimal, Message,
+};
 
 #[test]
 pub fn gen_verify() {
@@ -8,3 +15,71 @@ pub fn gen_verify() {
 
     assert_eq!(message, parsed);
 }
+
+#[test]
+pub fn notification_verify() {
+    let message = Message::Request(Request::SendNotification {
+        content: "Battery low".into(),
+        severity: NotificationSeverity::Warning,
+        category: NotificationCategory::Battery,
+    });
+    let raw = message.clone().to_raw();
+    let parsed = Message::from_raw(&raw).unwrap();
+
+    assert_eq!(message, parsed);
+}
+
+#[test]
+pub fn variant_tags_unchanged() {
+    // Tags of messages understood by nodes deployed before any other variants were added.
+    let requests = [
+        (Request::Ping, 0),
+        (Request::Bye, 1),
+        (
+            Request::Hello {
+                mac: Mac::new(1, 2, 3, 4, 5, 6),
+            },
+            2,
+        ),
+        (
+            Request::PostResults {
+                temperature: dec!(21.50),
+                humidity: 45,
+                air_pressure: None,
+            },
+            3,
+        ),
+        (
+            Request::PostStats {
+                battery: dec!(3.81),
+                wifi_ssid: "Weather".into(),
+                wifi_rssi: -67,
+            },
+            4,
+        ),
+        (Request::GetSettings([].into()), 6),
+    ];
+
+    for (request, tag) in requests {
+        let raw = Message::Request(request).to_raw();
+        assert_eq!(raw[4..8], u32::to_le_bytes(tag));
+    }
+
+    for (response, tag) in [
+        (Response::Pong, 0),
+        (Response::Ok, 1),
+        (Response::Reject, 2),
+    ] {
+        let raw = Message::Response(response).to_raw();
+        assert_eq!(raw[4..8], u32::to_le_bytes(tag));
+    }
+}
+
+#[test]
+pub fn bogus_length_rejected() {
+    // `GetSettings` claiming far more settings than the message contains.
+    let mut raw = Message::Request(Request::GetSettings([].into())).to_raw();
+    raw[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    assert_eq!(Message::from_raw(&raw), None);
+}